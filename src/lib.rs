@@ -1,16 +1,112 @@
+pub mod config {
+    use serde::Deserialize;
+    use std::{env, error::Error, fs, path::PathBuf};
+
+    const INSTANCES_ENV: &str = "YT_CLI_INSTANCES";
+
+    /// Settings read from `$XDG_CONFIG_HOME/yt-cli/config.toml`.
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
+    pub struct Config {
+        /// Piped API instances, tried in order.
+        pub instances: Vec<String>,
+        /// Country code for the trending page.
+        pub region: Option<String>,
+        /// Default stream quality, e.g. `<=1080p, prefer av1`.
+        pub quality: Option<String>,
+        /// `youtube` (default) or `piped` to play without yt-dlp.
+        pub stream_source: Option<String>,
+        /// Where downloads are saved, the user's download directory by default.
+        pub download_dir: Option<PathBuf>,
+        /// Names downloaded files, e.g. `{uploader} - {title}.{ext}`.
+        pub filename_template: Option<String>,
+        /// How many files download at the same time.
+        pub max_downloads: Option<usize>,
+        /// Subtitle languages in order of preference, e.g. `["en", "de"]`.
+        pub subtitles: Vec<String>,
+        /// SponsorBlock categories to skip, an empty list turns skipping off.
+        pub sponsorblock: Option<Vec<String>>,
+        /// `mpv`, `vlc` or a command template like `celluloid {url}`.
+        pub player: Option<String>,
+        /// How thumbnails are drawn: `auto` (default), `kitty`, `sixel`, `halfblocks` or `off`.
+        pub thumbnails: Option<String>,
+    }
+
+    impl Config {
+        pub fn path() -> Option<PathBuf> {
+            dirs::config_dir().map(|dir| dir.join("yt-cli").join("config.toml"))
+        }
+
+        /// Loads the config file if there is one, then applies environment overrides.
+        pub fn load() -> Result<Config, Box<dyn Error>> {
+            let mut config = match Self::path() {
+                Some(path) if path.exists() => {
+                    let body = fs::read_to_string(&path)?;
+                    toml::from_str(&body)
+                        .map_err(|e| format!("{}: {}", path.display(), e.message()))?
+                }
+                _ => Config::default(),
+            };
+
+            if let Ok(instances) = env::var(INSTANCES_ENV) {
+                config.instances = instances
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+            }
+
+            Ok(config)
+        }
+    }
+}
+
 pub mod backend {
+    use dashmap::DashMap;
     use reqwest::{
-        header::{HeaderValue, ACCEPT_LANGUAGE, USER_AGENT},
-        Client,
+        header::{HeaderMap, HeaderValue, ACCEPT_LANGUAGE, RETRY_AFTER, USER_AGENT},
+        Client, StatusCode,
+    };
+    use serde::{de::DeserializeOwned, Deserialize, Serialize};
+    use serde_json::Value;
+    use std::{
+        collections::HashSet,
+        error::Error,
+        fmt,
+        ops::Not,
+        str::FromStr,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
     };
-    use serde::{Deserialize, Serialize};
-    use std::{error::Error, ops::Not, process::Command, thread};
 
-    const TRENDING: &str = "https://pipedapi.kavin.rocks/trending?region=US";
-    const USR_AGENT: &str =
+    pub const DEFAULT_INSTANCES: &[&str] = &[
+        "https://pipedapi.kavin.rocks",
+        "https://pipedapi.adminforge.de",
+        "https://api.piped.yt",
+        "https://pipedapi.drgns.space",
+    ];
+    const TRENDING: &str = "/trending";
+    pub const DEFAULT_REGION: &str = "US";
+    /// SponsorBlock categories skipped unless the config says otherwise.
+    pub const SPONSOR_CATEGORIES: &[&str] = &["sponsor", "selfpromo"];
+    /// Country codes YouTube has a trending page for.
+    pub const REGIONS: &[&str] = &[
+        "AE", "AR", "AT", "AU", "AZ", "BA", "BD", "BE", "BG", "BH", "BO", "BR", "BY", "CA", "CH",
+        "CL", "CO", "CR", "CY", "CZ", "DE", "DK", "DO", "DZ", "EC", "EE", "EG", "ES", "FI", "FR",
+        "GB", "GE", "GH", "GR", "GT", "HK", "HN", "HR", "HU", "ID", "IE", "IL", "IN", "IQ", "IS",
+        "IT", "JM", "JO", "JP", "KE", "KR", "KW", "KZ", "LB", "LI", "LK", "LT", "LU", "LV", "LY",
+        "MA", "ME", "MK", "MT", "MX", "MY", "NG", "NI", "NL", "NO", "NP", "NZ", "OM", "PA", "PE",
+        "PG", "PH", "PK", "PL", "PR", "PT", "PY", "QA", "RO", "RS", "RU", "SA", "SE", "SG", "SI",
+        "SK", "SN", "SV", "TH", "TN", "TR", "TW", "TZ", "UA", "UG", "US", "UY", "VE", "VN", "YE",
+        "ZA", "ZW",
+    ];
+    pub(crate) const USR_AGENT: &str =
         "Mozilla/5.0 (X11; U; Linux armv7l; en-US; rv:1.9.2a1pre) Gecko/20090322 Fennec/1.0b2pre";
     const YT_URL: &str = "https://www.youtube.com";
-    const SEARCH_URL: &str = "https://pipedapi.kavin.rocks/search";
+    const SEARCH_URL: &str = "/search";
+    // an instance that keeps failing is skipped for 30s, doubling up to 8 minutes
+    const BASE_BACKOFF: Duration = Duration::from_secs(30);
+    const MAX_BACKOFF_SHIFT: u32 = 4;
 
     #[derive(Debug, Serialize, Deserialize)]
     pub struct Video {
@@ -26,189 +122,3690 @@ pub mod backend {
         is_short: Option<bool>,
         #[serde(rename = "uploaderVerified")]
         uploader_verified: Option<bool>,
+        #[serde(rename = "uploaderUrl")]
+        uploader_url: Option<String>,
+        name: Option<String>,
+        videos: Option<i64>,
+        subscribers: Option<i64>,
+        verified: Option<bool>,
+        thumbnail: Option<String>,
+        #[serde(rename = "uploaderAvatar")]
+        uploader_avatar: Option<String>,
     }
 
     #[derive(Debug, Serialize, Deserialize)]
     pub struct Response {
-        items: Vec<Video>,
-        nextpage: String,
+        items: Vec<Value>,
+        nextpage: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum ResultKind {
+        Video,
+        Short,
+        Livestream,
+        Playlist,
+        Channel,
+    }
+
+    impl ResultKind {
+        /// Videos, shorts and livestreams, everything with a `/streams` entry.
+        pub fn is_video(self) -> bool {
+            matches!(
+                self,
+                ResultKind::Video | ResultKind::Short | ResultKind::Livestream
+            )
+        }
+    }
+
+    /// The result filters the Piped search endpoint understands.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum SearchFilter {
+        #[default]
+        All,
+        Videos,
+        Channels,
+        Playlists,
+        MusicSongs,
+        MusicVideos,
+        MusicAlbums,
+        MusicPlaylists,
+    }
+
+    impl SearchFilter {
+        pub const ALL: [SearchFilter; 8] = [
+            SearchFilter::All,
+            SearchFilter::Videos,
+            SearchFilter::Channels,
+            SearchFilter::Playlists,
+            SearchFilter::MusicSongs,
+            SearchFilter::MusicVideos,
+            SearchFilter::MusicAlbums,
+            SearchFilter::MusicPlaylists,
+        ];
+
+        pub fn as_str(self) -> &'static str {
+            match self {
+                SearchFilter::All => "all",
+                SearchFilter::Videos => "videos",
+                SearchFilter::Channels => "channels",
+                SearchFilter::Playlists => "playlists",
+                SearchFilter::MusicSongs => "music_songs",
+                SearchFilter::MusicVideos => "music_videos",
+                SearchFilter::MusicAlbums => "music_albums",
+                SearchFilter::MusicPlaylists => "music_playlists",
+            }
+        }
+
+        /// The filter after this one, wrapping around.
+        pub fn next(self) -> Self {
+            let i = Self::ALL.iter().position(|f| *f == self).unwrap();
+            Self::ALL[(i + 1) % Self::ALL.len()]
+        }
+    }
+
+    impl fmt::Display for SearchFilter {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.as_str())
+        }
     }
 
+    impl FromStr for SearchFilter {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Self::ALL
+                .into_iter()
+                .find(|f| f.as_str() == s)
+                .ok_or_else(|| format!("unknown search filter: {}", s))
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct OrangeResult {
+        pub kind: ResultKind,
+        /// The YouTube video id, or the playlist or channel id.
+        pub id: String,
         pub title: String,
         pub url: String,
         pub duration: String,
         pub uploader: String,
         pub is_verified: bool,
+        /// The uploader's channel page, e.g. `/channel/UC...`.
+        pub uploader_url: Option<String>,
+        pub channel_id: Option<String>,
+        /// Number of videos, only known for playlists and channels.
+        pub video_count: Option<i64>,
+        /// Only known for channels.
+        pub subscribers: Option<i64>,
+        /// Thumbnail url, proxied through the instance. The avatar for channels.
+        #[serde(default)]
+        pub thumbnail: Option<String>,
+        #[serde(default)]
+        pub uploader_avatar: Option<String>,
     }
 
-    pub fn play_selection(selection: &str) {
-        let selection = selection.to_owned();
-        thread::spawn(move || {
-            Command::new("mpv")
-                .arg(&selection.trim())
-                .stdout(std::process::Stdio::null())
-                .stderr(std::process::Stdio::null())
-                .spawn()
-                .expect("Failed to execute command. Ensure mpv is installed.");
-        });
-    }
-
-    pub fn play_url(url: &str) {
-        let url = url.to_owned();
-        Command::new("mpv")
-            .arg(&url.trim())
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .spawn()
-            .expect("Failed to execute command. Ensure mpv is installed.");
-    }
-
-    #[tokio::main]
-    pub async fn get_search(search: &str) -> Result<Vec<OrangeResult>, Box<dyn Error>> {
-        if search.is_empty() {
-            return Ok(Vec::new());
-        }
-        let client = Client::new();
-
-        let resp = client
-            .get(SEARCH_URL)
-            .query(&[("q", search)])
-            .query(&[("filter", "all")])
-            .header(USER_AGENT, HeaderValue::from_str(USR_AGENT).unwrap())
-            .header(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.9"))
-            .send()
-            .await?;
-
-        let body = resp.text().await?;
-        let mut level_search: Response = serde_json::from_str(&body)?;
-
-        // run loop x times to get more results
-        for _ in 0..2 {
-            let resp = client
-                .get(SEARCH_URL)
-                .query(&[("q", search)])
-                .query(&[("filter", "all")])
-                .query(&[("nextpage", &level_search.nextpage)])
-                .header(USER_AGENT, HeaderValue::from_str(USR_AGENT).unwrap())
-                .header(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.9"))
+    /// One audio or video stream of a video, as listed by `/streams`.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Stream {
+        pub url: String,
+        #[serde(default)]
+        pub format: String,
+        #[serde(default)]
+        pub quality: String,
+        #[serde(default)]
+        pub mime_type: String,
+        pub codec: Option<String>,
+        #[serde(default)]
+        pub video_only: bool,
+        #[serde(default)]
+        pub bitrate: i64,
+        #[serde(default)]
+        pub width: u32,
+        #[serde(default)]
+        pub height: u32,
+        #[serde(default)]
+        pub fps: u32,
+        #[serde(default)]
+        pub content_length: i64,
+    }
+
+    impl Stream {
+        /// Piped marks HDR streams in the quality label, e.g. `1080p60 HDR`.
+        pub fn is_hdr(&self) -> bool {
+            self.quality.contains("HDR")
+        }
+
+        /// A short description like `1080p60 av01 HDR 123.4 MB`.
+        pub fn label(&self) -> String {
+            let mut label = format!("{} {}", self.quality, self.codec.as_deref().unwrap_or("?"));
+            if !self.video_only && self.height > 0 {
+                label.push_str(" (with audio)");
+            }
+            if self.content_length > 0 {
+                label.push_str(&format!(
+                    " {:.1} MB",
+                    self.content_length as f64 / 1_000_000.0
+                ));
+            }
+            label
+        }
+    }
+
+    /// A video codec family.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Codec {
+        Av1,
+        Vp9,
+        Avc,
+    }
+
+    impl Codec {
+        /// Whether a codec string as reported by Piped, e.g. `av01.0.08M.08`, belongs to this family.
+        pub fn matches(self, codec: &str) -> bool {
+            let codec = codec.to_ascii_lowercase();
+            match self {
+                Codec::Av1 => codec.starts_with("av01"),
+                Codec::Vp9 => codec.starts_with("vp9") || codec.starts_with("vp09"),
+                Codec::Avc => codec.starts_with("avc1"),
+            }
+        }
+    }
+
+    impl fmt::Display for Codec {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(match self {
+                Codec::Av1 => "av1",
+                Codec::Vp9 => "vp9",
+                Codec::Avc => "avc",
+            })
+        }
+    }
+
+    impl FromStr for Codec {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_ascii_lowercase().as_str() {
+                "av1" | "av01" => Ok(Codec::Av1),
+                "vp9" | "vp09" => Ok(Codec::Vp9),
+                "avc" | "avc1" | "h264" | "h.264" => Ok(Codec::Avc),
+                other => Err(format!("unknown codec: {}", other)),
+            }
+        }
+    }
+
+    /// Which video stream to play, parsed from a setting like `<=1080p, prefer av1, no hdr`.
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct QualityPreference {
+        /// Highest resolution to pick, by height.
+        pub max_height: Option<u32>,
+        /// Picked over other codecs of the same resolution.
+        pub codec: Option<Codec>,
+        /// `Some(true)` prefers HDR streams, `Some(false)` leaves them out.
+        pub hdr: Option<bool>,
+    }
+
+    impl QualityPreference {
+        /// The best video stream that fits, or the smallest one when none does.
+        pub fn pick<'a>(&self, streams: &'a [Stream]) -> Option<&'a Stream> {
+            let videos = streams.iter().filter(|s| s.height > 0);
+            let fits = videos
+                .clone()
+                .filter(|s| self.max_height.is_none_or(|max| s.height <= max))
+                .filter(|s| self.hdr != Some(false) || !s.is_hdr());
+            fits.max_by_key(|s| {
+                (
+                    s.height,
+                    self.codec
+                        .is_some_and(|c| s.codec.as_deref().is_some_and(|sc| c.matches(sc))),
+                    self.hdr == Some(true) && s.is_hdr(),
+                    s.fps,
+                    s.bitrate,
+                )
+            })
+            .or_else(|| videos.min_by_key(|s| (s.height, s.bitrate)))
+        }
+    }
+
+    impl fmt::Display for QualityPreference {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let mut parts = vec![];
+            if let Some(max_height) = self.max_height {
+                parts.push(format!("<={}p", max_height));
+            }
+            if let Some(codec) = self.codec {
+                parts.push(format!("prefer {}", codec));
+            }
+            match self.hdr {
+                Some(true) => parts.push("hdr".to_string()),
+                Some(false) => parts.push("no hdr".to_string()),
+                None => {}
+            }
+            if parts.is_empty() {
+                f.write_str("best")
+            } else {
+                f.write_str(&parts.join(", "))
+            }
+        }
+    }
+
+    impl FromStr for QualityPreference {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut preference = QualityPreference::default();
+            for part in s.split(',').map(|p| p.trim().to_ascii_lowercase()) {
+                if part.is_empty() || part == "best" {
+                    continue;
+                }
+                if let Some(codec) = part.strip_prefix("prefer ") {
+                    preference.codec = Some(codec.parse()?);
+                } else if part == "hdr" {
+                    preference.hdr = Some(true);
+                } else if part == "no hdr" || part == "sdr" {
+                    preference.hdr = Some(false);
+                } else {
+                    let height = part
+                        .trim_start_matches("<=")
+                        .trim_start_matches('≤')
+                        .trim()
+                        .trim_end_matches('p');
+                    preference.max_height = Some(
+                        height
+                            .parse()
+                            .map_err(|_| format!("invalid quality: {}", part))?,
+                    );
+                }
+            }
+            Ok(preference)
+        }
+    }
+
+    /// Where the player gets the media from.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum StreamSource {
+        /// The `youtube.com/watch` url, resolved by the player through yt-dlp.
+        #[default]
+        Youtube,
+        /// Stream urls and manifests from the Piped instance, played directly.
+        Piped,
+    }
+
+    impl StreamSource {
+        pub fn as_str(self) -> &'static str {
+            match self {
+                StreamSource::Youtube => "youtube",
+                StreamSource::Piped => "piped",
+            }
+        }
+    }
+
+    impl fmt::Display for StreamSource {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
+
+    impl FromStr for StreamSource {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_ascii_lowercase().as_str() {
+                "youtube" => Ok(StreamSource::Youtube),
+                "piped" => Ok(StreamSource::Piped),
+                other => Err(format!("unknown stream source: {}", other)),
+            }
+        }
+    }
+
+    /// The audio stream with the highest bitrate.
+    pub fn best_audio(streams: &[Stream]) -> Option<&Stream> {
+        streams.iter().max_by_key(|s| s.bitrate)
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Chapter {
+        pub title: String,
+        /// Offset in seconds.
+        pub start: i64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct StreamsResponse {
+        title: String,
+        description: Option<String>,
+        upload_date: Option<String>,
+        uploader: Option<String>,
+        uploader_url: Option<String>,
+        uploader_verified: Option<bool>,
+        #[serde(default)]
+        duration: i64,
+        #[serde(default)]
+        views: i64,
+        #[serde(default)]
+        likes: i64,
+        #[serde(default)]
+        dislikes: i64,
+        #[serde(default)]
+        chapters: Vec<Chapter>,
+        #[serde(default)]
+        related_streams: Vec<Value>,
+        #[serde(default)]
+        audio_streams: Vec<Stream>,
+        #[serde(default)]
+        video_streams: Vec<Stream>,
+        hls: Option<String>,
+        dash: Option<String>,
+        #[serde(default)]
+        subtitles: Vec<Subtitle>,
+    }
+
+    /// A caption track of a video, as listed by `/streams`.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Subtitle {
+        pub url: String,
+        /// `text/vtt` or `application/ttml+xml`.
+        #[serde(default)]
+        pub mime_type: String,
+        #[serde(default)]
+        pub name: String,
+        /// Language code, e.g. `en` or `pt-BR`.
+        #[serde(default)]
+        pub code: String,
+        #[serde(default)]
+        pub auto_generated: bool,
+    }
+
+    /// Everything `/streams/{id}` returns about a video.
+    #[derive(Debug, Clone)]
+    pub struct VideoDetails {
+        pub id: String,
+        pub title: String,
+        /// Plain text, the html markup is stripped.
+        pub description: String,
+        pub upload_date: Option<String>,
+        pub uploader: String,
+        pub uploader_url: Option<String>,
+        pub uploader_verified: bool,
+        /// Length in seconds.
+        pub duration: i64,
+        pub views: i64,
+        /// `-1` when the instance does not know.
+        pub likes: i64,
+        /// `-1` when the instance does not know.
+        pub dislikes: i64,
+        pub chapters: Vec<Chapter>,
+        pub related_streams: Vec<OrangeResult>,
+        pub audio_streams: Vec<Stream>,
+        pub video_streams: Vec<Stream>,
+        /// HLS manifest, proxied through the instance.
+        pub hls: Option<String>,
+        /// DASH manifest, not every instance provides one.
+        pub dash: Option<String>,
+        pub subtitles: Vec<Subtitle>,
+    }
+
+    impl VideoDetails {
+        /// A manifest mpv can play with adaptive quality, HLS first.
+        pub fn manifest(&self) -> Option<&str> {
+            self.hls
+                .as_deref()
+                .or(self.dash.as_deref())
+                .filter(|url| !url.is_empty())
+        }
+    }
+
+    /// Results decoded from one response.
+    #[derive(Default)]
+    pub struct Page {
+        pub items: Vec<OrangeResult>,
+        /// Items that were malformed and left out.
+        pub skipped: usize,
+        /// Token for the following page, `None` on the last one.
+        pub nextpage: Option<String>,
+    }
+
+    /// A search that remembers where it left off.
+    ///
+    /// Results already returned by an earlier page are dropped from later ones.
+    #[derive(Clone)]
+    pub struct SearchSession {
+        client: PipedClient,
+        query: String,
+        filter: SearchFilter,
+        nextpage: Option<String>,
+        started: bool,
+        seen: HashSet<String>,
+    }
+
+    impl SearchSession {
+        pub fn query(&self) -> &str {
+            &self.query
+        }
+
+        pub fn filter(&self) -> SearchFilter {
+            self.filter
+        }
+
+        /// True once the last page has been returned.
+        pub fn is_exhausted(&self) -> bool {
+            self.query.is_empty() || (self.started && self.nextpage.is_none())
+        }
+
+        /// Fetches the next page, or an empty one when the search is exhausted.
+        pub async fn next_page(&mut self) -> Result<Page, BackendError> {
+            if self.is_exhausted() {
+                return Ok(Page::default());
+            }
+
+            let mut page = self
+                .client
+                .search_page(&self.query, self.filter, self.nextpage.as_deref())
+                .await?;
+            self.started = true;
+            self.nextpage = page.nextpage.take();
+            page.items.retain(|item| self.seen.insert(item.url.clone()));
+            Ok(page)
+        }
+    }
+
+    /// A SponsorBlock segment of a video, from `/sponsors/{id}`.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Segment {
+        pub category: String,
+        /// `skip`, `mute`, `poi` or `full`.
+        #[serde(default)]
+        pub action_type: String,
+        /// Start and end in seconds.
+        pub segment: (f64, f64),
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct SponsorsResponse {
+        #[serde(default)]
+        segments: Vec<Segment>,
+    }
+
+    /// A channel tab other than the uploads, e.g. shorts or playlists.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ChannelTab {
+        pub name: String,
+        /// Opaque token Piped needs to load the tab.
+        pub data: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ChannelResponse {
+        id: String,
+        name: String,
+        description: Option<String>,
+        subscriber_count: Option<i64>,
+        verified: Option<bool>,
+        nextpage: Option<String>,
+        #[serde(default)]
+        related_streams: Vec<Value>,
+        #[serde(default)]
+        tabs: Vec<ChannelTab>,
+    }
+
+    /// A `/nextpage` response for channels and playlists.
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct NextPageResponse {
+        #[serde(default)]
+        related_streams: Vec<Value>,
+        nextpage: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct ChannelTabResponse {
+        #[serde(default)]
+        content: Vec<Value>,
+        nextpage: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PlaylistResponse {
+        name: String,
+        uploader: Option<String>,
+        uploader_url: Option<String>,
+        videos: Option<i64>,
+        nextpage: Option<String>,
+        #[serde(default)]
+        related_streams: Vec<Value>,
+    }
+
+    pub struct Playlist {
+        pub id: String,
+        pub name: String,
+        pub uploader: String,
+        pub uploader_url: Option<String>,
+        pub video_count: i64,
+        /// First page of videos, the rest comes from [`PipedClient::playlist_next_page`].
+        pub videos: Page,
+    }
+
+    pub struct Channel {
+        pub id: String,
+        pub name: String,
+        pub description: String,
+        /// `-1` when the instance does not know.
+        pub subscriber_count: i64,
+        pub verified: bool,
+        /// First page of uploads, the rest comes from [`PipedClient::channel_next_page`].
+        pub videos: Page,
+        pub tabs: Vec<ChannelTab>,
+    }
+
+    #[derive(Debug)]
+    pub enum BackendError {
+        /// The request could not be sent or the response could not be read.
+        Network(reqwest::Error),
+        /// The instance answered with an error status.
+        Status(StatusCode),
+        /// The response was not the JSON we expected.
+        Decode(serde_json::Error),
+        /// The instance answered 429, `retry_after` is taken from the header when present.
+        RateLimited { retry_after: Option<Duration> },
+        /// No instance could answer, with the error each one returned.
+        InstanceUnavailable(Vec<(String, BackendError)>),
+        /// The region is not a country code YouTube has trending videos for.
+        InvalidRegion(String),
+    }
+
+    impl fmt::Display for BackendError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                BackendError::Network(e) => write!(f, "network error: {}", e),
+                BackendError::Status(status) => write!(f, "http status {}", status),
+                BackendError::Decode(e) => write!(f, "unexpected response: {}", e),
+                BackendError::RateLimited {
+                    retry_after: Some(after),
+                } => write!(f, "rate limited, retry in {}s", after.as_secs()),
+                BackendError::RateLimited { retry_after: None } => write!(f, "rate limited"),
+                BackendError::InstanceUnavailable(errors) if errors.is_empty() => {
+                    write!(f, "no Piped instance configured")
+                }
+                BackendError::InstanceUnavailable(errors) => {
+                    write!(f, "no Piped instance answered")?;
+                    for (i, (instance, e)) in errors.iter().enumerate() {
+                        let sep = if i == 0 { " (" } else { ", " };
+                        write!(f, "{}{}: {}", sep, instance_name(instance), e)?;
+                    }
+                    write!(f, ")")
+                }
+                BackendError::InvalidRegion(region) => write!(f, "unknown region: {}", region),
+            }
+        }
+    }
+
+    impl BackendError {
+        /// Whether the error says something about the instance rather than the request.
+        ///
+        /// A 404 for a removed video would be the same on every instance, so only network
+        /// errors, server errors, rate limits and garbled responses move on to the next one.
+        pub fn is_instance_fault(&self) -> bool {
+            match self {
+                BackendError::Status(status) => status.is_server_error(),
+                BackendError::Network(_)
+                | BackendError::Decode(_)
+                | BackendError::RateLimited { .. }
+                | BackendError::InstanceUnavailable(_) => true,
+                BackendError::InvalidRegion(_) => false,
+            }
+        }
+    }
+
+    impl Error for BackendError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                BackendError::Network(e) => Some(e),
+                BackendError::Decode(e) => Some(e),
+                _ => None,
+            }
+        }
+    }
+
+    impl From<reqwest::Error> for BackendError {
+        fn from(e: reqwest::Error) -> Self {
+            BackendError::Network(e)
+        }
+    }
+
+    impl From<serde_json::Error> for BackendError {
+        fn from(e: serde_json::Error) -> Self {
+            BackendError::Decode(e)
+        }
+    }
+
+    #[derive(Debug, Default, Clone, Copy)]
+    struct Health {
+        failures: u32,
+        retry_at: Option<Instant>,
+    }
+
+    /// The Piped instances to query, with per-instance health tracking.
+    ///
+    /// Every request walks the list in order, skipping instances that failed recently,
+    /// and moves on to the next one when an instance does not answer.
+    pub struct Instances {
+        urls: Vec<String>,
+        health: DashMap<String, Health>,
+        last_used: Mutex<Option<String>>,
+    }
+
+    impl Instances {
+        pub fn new(urls: Vec<String>) -> Self {
+            let mut urls: Vec<String> = urls
+                .into_iter()
+                .map(|url| url.trim().trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty())
+                .collect();
+            if urls.is_empty() {
+                urls = DEFAULT_INSTANCES.iter().map(|s| s.to_string()).collect();
+            }
+            Self {
+                urls,
+                health: DashMap::new(),
+                last_used: Mutex::new(None),
+            }
+        }
+
+        pub fn urls(&self) -> &[String] {
+            &self.urls
+        }
+
+        /// The instance that answered the last successful request.
+        pub fn last_used(&self) -> Option<String> {
+            self.last_used.lock().unwrap().clone()
+        }
+
+        /// Healthy instances in configured order, followed by the ones still
+        /// backing off, soonest to recover first.
+        fn ordered(&self) -> Vec<String> {
+            let now = Instant::now();
+            let mut healthy = vec![];
+            let mut backing_off = vec![];
+            for url in &self.urls {
+                match self.health.get(url).and_then(|h| h.retry_at) {
+                    Some(retry_at) if retry_at > now => backing_off.push((retry_at, url.clone())),
+                    _ => healthy.push(url.clone()),
+                }
+            }
+            backing_off.sort();
+            healthy.extend(backing_off.into_iter().map(|(_, url)| url));
+            healthy
+        }
+
+        fn mark_ok(&self, url: &str) {
+            self.health.insert(url.to_string(), Health::default());
+            *self.last_used.lock().unwrap() = Some(url.to_string());
+        }
+
+        fn mark_failed(&self, url: &str) {
+            let mut health = self.health.entry(url.to_string()).or_default();
+            health.failures += 1;
+            let backoff = BASE_BACKOFF * (1 << (health.failures - 1).min(MAX_BACKOFF_SHIFT));
+            health.retry_at = Some(Instant::now() + backoff);
+        }
+    }
+
+    /// Normalizes a country code to upper case and checks it against [`REGIONS`].
+    pub fn validate_region(region: &str) -> Result<String, BackendError> {
+        let region = region.trim().to_uppercase();
+        if REGIONS.contains(&region.as_str()) {
+            Ok(region)
+        } else {
+            Err(BackendError::InvalidRegion(region))
+        }
+    }
+
+    /// Strips the scheme so an instance url fits in the status line.
+    pub fn instance_name(url: &str) -> &str {
+        url.trim_start_matches("https://")
+            .trim_start_matches("http://")
+    }
+
+    /// A Piped API client sharing one connection pool across requests.
+    ///
+    /// Cloning is cheap, clones share the pool and the instance health.
+    #[derive(Clone)]
+    pub struct PipedClient {
+        client: Client,
+        instances: Arc<Instances>,
+    }
+
+    impl PipedClient {
+        pub fn new(instances: Instances) -> Self {
+            let mut headers = HeaderMap::new();
+            headers.insert(USER_AGENT, HeaderValue::from_static(USR_AGENT));
+            headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.9"));
+            let client = Client::builder()
+                .default_headers(headers)
+                .build()
+                .expect("Failed to build the http client");
+            Self {
+                client,
+                instances: Arc::new(instances),
+            }
+        }
+
+        pub fn instances(&self) -> &Instances {
+            &self.instances
+        }
+
+        async fn fetch_one<T: DeserializeOwned>(
+            &self,
+            base: &str,
+            path: &str,
+            query: &[(&str, &str)],
+        ) -> Result<T, BackendError> {
+            let resp = self
+                .client
+                .get(format!("{}{}", base, path))
+                .query(query)
                 .send()
                 .await?;
 
+            let status = resp.status();
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                let retry_after = resp
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse().ok())
+                    .map(Duration::from_secs);
+                return Err(BackendError::RateLimited { retry_after });
+            }
+            if !status.is_success() {
+                return Err(BackendError::Status(status));
+            }
+
             let body = resp.text().await?;
-            let response: Response = serde_json::from_str(&body)?;
-            level_search.items.extend(response.items);
+            Ok(serde_json::from_str(&body)?)
         }
 
-        let mut results: Vec<OrangeResult> = vec![];
+        /// Tries each instance in turn until one returns a body that decodes as `T`.
+        async fn fetch<T: DeserializeOwned>(
+            &self,
+            path: &str,
+            query: &[(&str, &str)],
+        ) -> Result<T, BackendError> {
+            let mut errors = vec![];
 
-        if level_search.items.is_empty() {
-            return Ok(Vec::new());
+            for base in self.instances.ordered() {
+                match self.fetch_one(&base, path, query).await {
+                    Ok(body) => {
+                        self.instances.mark_ok(&base);
+                        return Ok(body);
+                    }
+                    Err(e) if e.is_instance_fault() => {
+                        self.instances.mark_failed(&base);
+                        errors.push((base, e));
+                    }
+                    // the instance is fine, the request is not
+                    Err(e) => return Err(e),
+                }
+            }
+
+            Err(BackendError::InstanceUnavailable(errors))
         }
 
-        let mut videos: Vec<Video> = vec![];
+        /// Starts a search, pages are fetched as [`SearchSession::next_page`] is called.
+        pub fn search(&self, search: &str, filter: SearchFilter) -> SearchSession {
+            SearchSession {
+                client: self.clone(),
+                query: search.to_string(),
+                filter,
+                nextpage: None,
+                started: false,
+                seen: HashSet::new(),
+            }
+        }
 
-        // push the videos to the videos vector if the title is "stream"
-        for video in level_search.items {
-            if video.video_type.to_lowercase() == "stream" && video.is_short.is_none().not() {
-                videos.push(video);
+        /// Fetches one page of search results, the first one when `nextpage` is `None`.
+        pub async fn search_page(
+            &self,
+            search: &str,
+            filter: SearchFilter,
+            nextpage: Option<&str>,
+        ) -> Result<Page, BackendError> {
+            if search.is_empty() {
+                return Ok(Page::default());
             }
+
+            let response: Response = match nextpage {
+                None => {
+                    self.fetch(SEARCH_URL, &[("q", search), ("filter", filter.as_str())])
+                        .await?
+                }
+                Some(nextpage) => {
+                    self.fetch(
+                        &format!("/nextpage{}", SEARCH_URL),
+                        &[
+                            ("q", search),
+                            ("filter", filter.as_str()),
+                            ("nextpage", nextpage),
+                        ],
+                    )
+                    .await?
+                }
+            };
+
+            let mut page = to_page(response.items);
+            page.nextpage = response.nextpage;
+            Ok(page)
         }
 
-        if videos.is_empty() {
-            return Ok(Vec::new());
+        /// Completions for a partly typed query from `/suggestions`.
+        pub async fn suggestions(&self, query: &str) -> Result<Vec<String>, BackendError> {
+            if query.trim().is_empty() {
+                return Ok(Vec::new());
+            }
+            self.fetch("/suggestions", &[("query", query)]).await
         }
 
-        let duration = |d: i32| -> String {
-            let minutes = d / 60;
-            let seconds = d % 60;
-            if seconds < 10 && minutes > 10 {
-                return format!("{}:0{}", minutes, seconds);
+        /// The segments of a video to skip, in the given SponsorBlock categories.
+        ///
+        /// Only the preferred instance is asked, a video without segments can answer with a
+        /// body that does not decode, which should not count against every instance.
+        pub async fn sponsors(
+            &self,
+            id: &str,
+            categories: &[String],
+        ) -> Result<Vec<Segment>, BackendError> {
+            let Some(base) = self.instances.ordered().into_iter().next() else {
+                return Ok(Vec::new());
+            };
+            let categories = serde_json::to_string(categories)?;
+            let response = self
+                .fetch_one::<SponsorsResponse>(
+                    &base,
+                    &format!("/sponsors/{}", id),
+                    &[("category", &categories)],
+                )
+                .await;
+            match response {
+                Ok(response) => Ok(response
+                    .segments
+                    .into_iter()
+                    .filter(|s| s.action_type.is_empty() || s.action_type == "skip")
+                    .collect()),
+                // no segments for this video
+                Err(BackendError::Status(StatusCode::NOT_FOUND) | BackendError::Decode(_)) => {
+                    Ok(Vec::new())
+                }
+                Err(e) => Err(e),
             }
-            if seconds > 10 && minutes < 10 {
-                return format!("0{}:{}", minutes, seconds);
+        }
+
+        /// Trending videos for a country, `region` is checked with [`validate_region`] first.
+        pub async fn trending(&self, region: &str) -> Result<Page, BackendError> {
+            let region = validate_region(region)?;
+            let items: Vec<Value> = self.fetch(TRENDING, &[("region", &region)]).await?;
+
+            Ok(to_page(items))
+        }
+
+        /// Fetches everything Piped knows about one video from `/streams/{id}`.
+        pub async fn video_details(&self, id: &str) -> Result<VideoDetails, BackendError> {
+            let streams: StreamsResponse = self.fetch(&format!("/streams/{}", id), &[]).await?;
+
+            Ok(VideoDetails {
+                id: id.to_string(),
+                title: streams.title,
+                description: html_to_text(&streams.description.unwrap_or_default()),
+                upload_date: streams.upload_date,
+                uploader: streams.uploader.unwrap_or_default(),
+                uploader_url: streams.uploader_url,
+                uploader_verified: streams.uploader_verified.unwrap_or(false),
+                duration: streams.duration,
+                views: streams.views,
+                likes: streams.likes,
+                dislikes: streams.dislikes,
+                chapters: streams.chapters,
+                related_streams: to_page(streams.related_streams).items,
+                audio_streams: streams.audio_streams,
+                video_streams: streams.video_streams,
+                hls: streams.hls,
+                dash: streams.dash,
+                subtitles: streams.subtitles,
+            })
+        }
+
+        /// Downloads a caption track and converts it to SRT.
+        pub async fn subtitle(&self, subtitle: &Subtitle) -> Result<String, BackendError> {
+            let resp = self.client.get(&subtitle.url).send().await?;
+            if !resp.status().is_success() {
+                return Err(BackendError::Status(resp.status()));
             }
-            format!("{}:{}", minutes, seconds)
-        };
+            let body = resp.text().await?;
+            Ok(crate::subtitles::to_srt(&body, &subtitle.mime_type))
+        }
 
-        for video in videos {
-            let title = video.title.as_ref().unwrap().to_string().replace("//", "");
-            let watch_id = video.url.to_string();
-            let video_url = format!("{}{}", YT_URL, watch_id);
-            let vid_duration = duration(video.duration.unwrap());
-            let uploader = video.uploader_name.unwrap().to_string();
-            let is_verified = video.uploader_verified.unwrap();
-            results.push(OrangeResult {
-                title,
-                url: video_url,
-                duration: vid_duration,
-                uploader,
-                is_verified,
-            });
+        /// Fetches the image behind a thumbnail or avatar url.
+        pub async fn thumbnail(&self, url: &str) -> Result<Vec<u8>, BackendError> {
+            let resp = self.client.get(url).send().await?;
+            if !resp.status().is_success() {
+                return Err(BackendError::Status(resp.status()));
+            }
+            Ok(resp.bytes().await?.to_vec())
         }
 
-        Ok(results)
-    }
+        /// Fetches a channel with its first page of uploads from `/channel/{id}`.
+        pub async fn channel(&self, id: &str) -> Result<Channel, BackendError> {
+            let channel: ChannelResponse = self.fetch(&format!("/channel/{}", id), &[]).await?;
+
+            let mut videos = to_page(channel.related_streams);
+            videos.nextpage = channel.nextpage;
+
+            Ok(Channel {
+                id: channel.id,
+                name: channel.name,
+                description: channel.description.unwrap_or_default(),
+                subscriber_count: channel.subscriber_count.unwrap_or(-1),
+                verified: channel.verified.unwrap_or(false),
+                videos,
+                tabs: channel.tabs,
+            })
+        }
 
-    #[tokio::main]
-    pub async fn get_trending() -> Result<Vec<OrangeResult>, Box<dyn Error>> {
-        let client = Client::new();
-        let resp = client
-            .get(TRENDING)
-            .header(USER_AGENT, HeaderValue::from_str(USR_AGENT).unwrap())
-            .header(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.9"))
-            .send()
-            .await?;
+        /// Loads the uploads following `nextpage`.
+        pub async fn channel_next_page(
+            &self,
+            id: &str,
+            nextpage: &str,
+        ) -> Result<Page, BackendError> {
+            let response: NextPageResponse = self
+                .fetch(
+                    &format!("/nextpage/channel/{}", id),
+                    &[("nextpage", nextpage)],
+                )
+                .await?;
+
+            let mut page = to_page(response.related_streams);
+            page.nextpage = response.nextpage;
+            Ok(page)
+        }
+
+        /// Fetches a playlist with its first page of videos from `/playlists/{id}`.
+        pub async fn playlist(&self, id: &str) -> Result<Playlist, BackendError> {
+            let playlist: PlaylistResponse = self.fetch(&format!("/playlists/{}", id), &[]).await?;
+
+            let mut videos = to_page(playlist.related_streams);
+            videos.nextpage = playlist.nextpage;
+
+            Ok(Playlist {
+                id: id.to_string(),
+                name: playlist.name,
+                uploader: playlist.uploader.unwrap_or_default(),
+                uploader_url: playlist.uploader_url,
+                video_count: playlist.videos.unwrap_or(-1),
+                videos,
+            })
+        }
+
+        /// Loads the playlist videos following `nextpage`.
+        pub async fn playlist_next_page(
+            &self,
+            id: &str,
+            nextpage: &str,
+        ) -> Result<Page, BackendError> {
+            let response: NextPageResponse = self
+                .fetch(
+                    &format!("/nextpage/playlists/{}", id),
+                    &[("nextpage", nextpage)],
+                )
+                .await?;
 
-        let body = resp.text().await?;
+            let mut page = to_page(response.related_streams);
+            page.nextpage = response.nextpage;
+            Ok(page)
+        }
 
-        let response: Vec<Video> = serde_json::from_str(&body)?;
-        let mut results: Vec<OrangeResult> = vec![];
+        /// Loads a channel tab, or the page of it following `nextpage`.
+        pub async fn channel_tab(
+            &self,
+            tab: &ChannelTab,
+            nextpage: Option<&str>,
+        ) -> Result<Page, BackendError> {
+            let mut query = vec![("data", tab.data.as_str())];
+            if let Some(nextpage) = nextpage {
+                query.push(("nextpage", nextpage));
+            }
+            let response: ChannelTabResponse = self.fetch("/channels/tabs", &query).await?;
 
-        if response.is_empty() {
-            return Ok(Vec::new());
+            let mut page = to_page(response.content);
+            page.nextpage = response.nextpage;
+            Ok(page)
         }
+    }
+
+    pub fn watch_url(id: &str) -> String {
+        format!("{}/watch?v={}", YT_URL, id)
+    }
+
+    /// Pulls the channel id out of a `/channel/{id}` url.
+    pub fn channel_id(url: &str) -> Option<&str> {
+        url.rsplit_once("/channel/")
+            .map(|(_, id)| id.trim_end_matches('/'))
+            .filter(|id| !id.is_empty())
+    }
 
-        let mut videos: Vec<Video> = vec![];
+    /// Pulls the playlist id out of a `/playlist?list=` url.
+    fn playlist_id(url: &str) -> Option<&str> {
+        let (_, query) = url.split_once('?')?;
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("list="))
+            .filter(|id| !id.is_empty())
+    }
+
+    /// Pulls the video id out of a `/watch?v=` url.
+    pub fn video_id(url: &str) -> Option<&str> {
+        let (_, query) = url.split_once('?')?;
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("v="))
+            .filter(|id| !id.is_empty())
+    }
 
-        for video in response {
-            if video.video_type.to_lowercase() == "stream" && video.is_short.is_none().not() {
-                videos.push(video);
+    /// Piped descriptions are html, this keeps the line breaks and drops the markup.
+    pub(crate) fn html_to_text(html: &str) -> String {
+        let html = html.replace("<br>", "\n").replace("<br/>", "\n");
+        let mut text = String::with_capacity(html.len());
+        let mut in_tag = false;
+        for c in html.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                c if !in_tag => text.push(c),
+                _ => {}
             }
         }
+        text.replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&nbsp;", " ")
+            .replace("&amp;", "&")
+    }
+
+    /// Formats seconds as `m:ss`, or `h:mm:ss` for anything an hour or longer.
+    pub fn format_duration(seconds: i64) -> String {
+        let seconds = seconds.max(0);
+        let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+        if hours > 0 {
+            format!("{}:{:02}:{:02}", hours, minutes, seconds)
+        } else {
+            format!("{}:{:02}", minutes, seconds)
+        }
+    }
+
+    impl Video {
+        fn is_stream(&self) -> bool {
+            self.video_type.to_lowercase() == "stream" && self.is_short.is_none().not()
+        }
+
+        fn is_playlist(&self) -> bool {
+            self.video_type.to_lowercase() == "playlist"
+        }
 
-        if videos.is_empty() {
-            return Ok(Vec::new());
+        fn is_channel(&self) -> bool {
+            self.video_type.to_lowercase() == "channel"
         }
 
-        let duration = |d: i32| -> String {
-            let minutes = d / 60;
-            let seconds = d % 60;
-            if seconds < 10 && minutes > 10 {
-                return format!("{}:0{}", minutes, seconds);
+        fn into_result(self) -> Option<OrangeResult> {
+            if self.is_channel() {
+                let id = channel_id(&self.url)?.to_string();
+                let name = self.name?;
+                return Some(OrangeResult {
+                    kind: ResultKind::Channel,
+                    url: format!("{}/channel/{}", YT_URL, id),
+                    title: name.clone(),
+                    duration: String::new(),
+                    uploader: name,
+                    is_verified: self.verified.unwrap_or(false),
+                    uploader_url: Some(self.url),
+                    channel_id: Some(id.clone()),
+                    id,
+                    video_count: self.videos.filter(|n| *n >= 0),
+                    subscribers: self.subscribers.filter(|n| *n >= 0),
+                    uploader_avatar: self.thumbnail.clone(),
+                    thumbnail: self.thumbnail,
+                });
             }
-            if seconds > 10 && minutes < 10 {
-                return format!("0{}:{}", minutes, seconds);
+
+            let channel_id = self
+                .uploader_url
+                .as_deref()
+                .and_then(channel_id)
+                .map(|id| id.to_string());
+
+            if self.is_playlist() {
+                let id = playlist_id(&self.url)?.to_string();
+                return Some(OrangeResult {
+                    kind: ResultKind::Playlist,
+                    url: format!("{}/playlist?list={}", YT_URL, id),
+                    id,
+                    title: self.name?,
+                    duration: String::new(),
+                    uploader: self.uploader_name.unwrap_or_default(),
+                    is_verified: self.uploader_verified.unwrap_or(false),
+                    uploader_url: self.uploader_url,
+                    channel_id,
+                    video_count: self.videos,
+                    subscribers: None,
+                    thumbnail: self.thumbnail,
+                    uploader_avatar: self.uploader_avatar,
+                });
             }
-            format!("{}:{}", minutes, seconds)
-        };
 
-        for video in videos {
-            let title = video.title.as_ref().unwrap().to_string().replace("//", "");
-            let watch_id = video.url.to_string();
-            let video_url = format!("{}{}", YT_URL, watch_id);
-            let vid_duration = duration(video.duration.unwrap());
-            let is_verified = video.uploader_verified.unwrap();
-            let uploader = video.uploader_name.unwrap().to_string();
-            results.push(OrangeResult {
-                title,
-                url: video_url,
-                duration: vid_duration,
-                uploader,
-                is_verified,
-            });
+            let duration = self.duration?;
+            let kind = if duration < 0 {
+                ResultKind::Livestream
+            } else if self.is_short == Some(true) {
+                ResultKind::Short
+            } else {
+                ResultKind::Video
+            };
+            Some(OrangeResult {
+                kind,
+                id: video_id(&self.url)?.to_string(),
+                title: self.title?.replace("//", ""),
+                url: format!("{}{}", YT_URL, self.url),
+                duration: if kind == ResultKind::Livestream {
+                    "LIVE".to_string()
+                } else {
+                    format_duration(duration.into())
+                },
+                uploader: self.uploader_name?,
+                is_verified: self.uploader_verified.unwrap_or(false),
+                uploader_url: self.uploader_url,
+                channel_id,
+                video_count: None,
+                subscribers: None,
+                thumbnail: self.thumbnail,
+                uploader_avatar: self.uploader_avatar,
+            })
         }
+    }
 
-        Ok(results)
+    /// Decodes the stream items one by one, so a single malformed item only gets
+    /// counted as skipped instead of failing the whole page.
+    fn to_page(items: Vec<Value>) -> Page {
+        let mut page = Page::default();
+
+        for item in items {
+            match serde_json::from_value::<Video>(item) {
+                Ok(video) if !(video.is_stream() || video.is_playlist() || video.is_channel()) => {}
+                Ok(video) => match video.into_result() {
+                    Some(result) => page.items.push(result),
+                    None => page.skipped += 1,
+                },
+                Err(_) => page.skipped += 1,
+            }
+        }
+
+        page
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn instances() -> Instances {
+            Instances::new(vec![
+                "https://a.example/".to_string(),
+                "https://b.example".to_string(),
+                "https://c.example".to_string(),
+            ])
+        }
+
+        #[test]
+        fn ordered_keeps_configured_order() {
+            assert_eq!(
+                instances().ordered(),
+                [
+                    "https://a.example",
+                    "https://b.example",
+                    "https://c.example"
+                ]
+            );
+        }
+
+        #[test]
+        fn ordered_moves_failed_instances_last() {
+            let instances = instances();
+            instances.mark_failed("https://a.example");
+            instances.mark_failed("https://a.example");
+            instances.mark_failed("https://b.example");
+            // b recovers first, its backoff is shorter
+            assert_eq!(
+                instances.ordered(),
+                [
+                    "https://c.example",
+                    "https://b.example",
+                    "https://a.example"
+                ]
+            );
+
+            instances.mark_ok("https://a.example");
+            assert_eq!(
+                instances.ordered(),
+                [
+                    "https://a.example",
+                    "https://c.example",
+                    "https://b.example"
+                ]
+            );
+            assert_eq!(instances.last_used().as_deref(), Some("https://a.example"));
+        }
+
+        #[test]
+        fn backoff_doubles_up_to_the_limit() {
+            let instances = instances();
+            let url = "https://a.example";
+            let mut backoffs = vec![];
+            for _ in 0..7 {
+                let before = Instant::now();
+                instances.mark_failed(url);
+                let retry_at = instances.health.get(url).unwrap().retry_at.unwrap();
+                // round down to whole seconds, the test takes a few microseconds
+                backoffs.push((retry_at - before).as_secs());
+            }
+            assert_eq!(backoffs, [30, 60, 120, 240, 480, 480, 480]);
+        }
+
+        #[test]
+        fn to_page_skips_and_counts_malformed_items() {
+            let items = serde_json::json!([
+                {
+                    "type": "stream",
+                    "url": "/watch?v=dQw4w9WgXcQ",
+                    "title": "A video",
+                    "duration": 212,
+                    "uploaderName": "Someone",
+                    "uploaderVerified": true,
+                    "uploaderUrl": "/channel/UCabc",
+                    "isShort": false
+                },
+                {
+                    "type": "stream",
+                    "url": "/watch?v=notitle0000",
+                    "duration": 60,
+                    "uploaderName": "Someone",
+                    "isShort": false
+                },
+                {
+                    "type": "stream",
+                    "url": "/watch?v=badduration",
+                    "title": "Bad duration",
+                    "duration": "3:32",
+                    "uploaderName": "Someone",
+                    "isShort": false
+                },
+                {
+                    "type": "playlist",
+                    "url": "/playlist?list=PLxyz",
+                    "name": "A playlist",
+                    "uploaderName": "Someone",
+                    "videos": 12
+                },
+                {
+                    "type": "channel",
+                    "url": "/channel/UCdef",
+                    "name": "A channel",
+                    "subscribers": 1000,
+                    "verified": true
+                }
+            ]);
+            let serde_json::Value::Array(items) = items else {
+                unreachable!()
+            };
+
+            let page = to_page(items);
+            assert_eq!(page.items.len(), 3);
+            assert_eq!(page.skipped, 2);
+
+            let kinds: Vec<ResultKind> = page.items.iter().map(|r| r.kind).collect();
+            assert_eq!(
+                kinds,
+                [ResultKind::Video, ResultKind::Playlist, ResultKind::Channel]
+            );
+            assert_eq!(page.items[0].id, "dQw4w9WgXcQ");
+            assert_eq!(page.items[0].channel_id.as_deref(), Some("UCabc"));
+            assert_eq!(page.items[1].id, "PLxyz");
+            assert_eq!(page.items[2].id, "UCdef");
+        }
+
+        fn stream(quality: &str, codec: &str, height: u32, fps: u32) -> Stream {
+            Stream {
+                url: format!("https://proxy.example/{}-{}", quality, codec),
+                format: String::new(),
+                quality: quality.to_string(),
+                mime_type: String::new(),
+                codec: Some(codec.to_string()),
+                video_only: true,
+                bitrate: height as i64 * 1000,
+                width: height * 16 / 9,
+                height,
+                fps,
+                content_length: 0,
+            }
+        }
+
+        fn streams() -> Vec<Stream> {
+            vec![
+                stream("2160p", "vp09.00.51.08", 2160, 30),
+                stream("1080p60 HDR", "av01.0.09M.10", 1080, 60),
+                stream("1080p", "avc1.640028", 1080, 30),
+                stream("1080p", "av01.0.08M.08", 1080, 30),
+                stream("720p", "avc1.4d401f", 720, 30),
+                stream("360p", "avc1.42001e", 360, 30),
+            ]
+        }
+
+        #[test]
+        fn quality_parses_settings() {
+            let preference: QualityPreference = "<=1080p, prefer av1, no hdr".parse().unwrap();
+            assert_eq!(
+                preference,
+                QualityPreference {
+                    max_height: Some(1080),
+                    codec: Some(Codec::Av1),
+                    hdr: Some(false),
+                }
+            );
+            assert_eq!(preference.to_string(), "<=1080p, prefer av1, no hdr");
+
+            let preference: QualityPreference = "≤720p, HDR".parse().unwrap();
+            assert_eq!(preference.max_height, Some(720));
+            assert_eq!(preference.hdr, Some(true));
+            assert_eq!("best".parse(), Ok(QualityPreference::default()));
+        }
+
+        #[test]
+        fn quality_rejects_invalid_settings() {
+            assert!("<=tall".parse::<QualityPreference>().is_err());
+            assert!("prefer theora".parse::<QualityPreference>().is_err());
+        }
+
+        #[test]
+        fn quality_picks_the_preferred_codec_within_the_limit() {
+            let streams = streams();
+            let pick = |s: &str| {
+                s.parse::<QualityPreference>()
+                    .unwrap()
+                    .pick(&streams)
+                    .map(|s| (s.quality.clone(), s.codec.clone().unwrap()))
+            };
+            assert_eq!(
+                pick("<=1080p, prefer av1, no hdr"),
+                Some(("1080p".to_string(), "av01.0.08M.08".to_string()))
+            );
+            assert_eq!(
+                pick("<=1080p, prefer av1, hdr"),
+                Some(("1080p60 HDR".to_string(), "av01.0.09M.10".to_string()))
+            );
+            assert_eq!(
+                pick("<=1080p, prefer avc, no hdr"),
+                Some(("1080p".to_string(), "avc1.640028".to_string()))
+            );
+            assert_eq!(pick("best").unwrap().0, "2160p");
+        }
+
+        #[test]
+        fn quality_falls_back_to_the_smallest_stream() {
+            let streams = streams();
+            let preference: QualityPreference = "<=240p".parse().unwrap();
+            assert_eq!(preference.pick(&streams).unwrap().quality, "360p");
+            assert!(preference.pick(&[]).is_none());
+        }
+
+        #[test]
+        fn only_instance_faults_fail_over() {
+            assert!(BackendError::Status(StatusCode::BAD_GATEWAY).is_instance_fault());
+            assert!(BackendError::RateLimited { retry_after: None }.is_instance_fault());
+            assert!(!BackendError::Status(StatusCode::NOT_FOUND).is_instance_fault());
+            assert!(!BackendError::Status(StatusCode::BAD_REQUEST).is_instance_fault());
+        }
+    }
+}
+
+pub mod player {
+    use std::{
+        collections::VecDeque,
+        env, fmt,
+        io::{self, BufRead, BufReader, ErrorKind},
+        path::{Path, PathBuf},
+        process::{self, Child, Command, ExitStatus, Stdio},
+        sync::{Arc, Mutex},
+        thread::{self, JoinHandle},
+    };
+
+    /// Players with built-in support, in order of preference.
+    pub const BUILTIN: &[&str] = &["mpv", "vlc"];
+
+    /// How the urls should be played.
+    #[derive(Debug, Clone, Default)]
+    pub struct PlayOptions {
+        /// Play without opening a video window.
+        pub audio_only: bool,
+        /// A separate audio track for video-only streams.
+        pub audio_file: Option<String>,
+        /// Shown instead of the stream url.
+        pub title: Option<String>,
+        /// The urls are media streams rather than pages that need yt-dlp.
+        pub direct: bool,
+        /// Where to start playing, in seconds.
+        pub start: Option<f64>,
+        /// Subtitle files loaded next to the video.
+        pub sub_files: Vec<PathBuf>,
+    }
+
+    /// Something that can play a list of urls, one after another.
+    pub trait Player: Send + Sync {
+        /// The name shown to the user.
+        fn name(&self) -> &str;
+
+        /// The executable looked up in `PATH`.
+        fn binary(&self) -> &str;
+
+        /// Builds the command that plays `urls` in order.
+        fn command(&self, urls: &[String], options: &PlayOptions) -> Command;
+
+        /// The IPC socket the player listens on, only mpv has one.
+        fn ipc_socket(&self) -> Option<&Path> {
+            None
+        }
+
+        /// Whether [`PlayOptions::audio_file`] is played along with a video-only stream.
+        fn separate_audio(&self) -> bool {
+            true
+        }
+
+        fn is_installed(&self) -> bool {
+            is_in_path(self.binary())
+        }
+
+        /// Starts playing in the background, the process reports how it ended.
+        fn spawn(&self, urls: &[String], options: &PlayOptions) -> io::Result<PlayerProcess> {
+            let child = self
+                .command(urls, options)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|e| match e.kind() {
+                    ErrorKind::NotFound => io::Error::new(
+                        ErrorKind::NotFound,
+                        format!("{} not found, ensure it is installed", self.binary()),
+                    ),
+                    _ => e,
+                })?;
+            Ok(PlayerProcess::new(self.name(), child))
+        }
+
+        /// Plays and waits for the player to exit.
+        fn play_blocking(&self, urls: &[String], options: &PlayOptions) -> io::Result<Outcome> {
+            self.spawn(urls, options)?.wait()
+        }
+    }
+
+    /// How many lines of the player's stderr are kept.
+    const STDERR_LINES: usize = 20;
+
+    /// A running player, its stderr is collected so a failure can be explained.
+    pub struct PlayerProcess {
+        name: String,
+        child: Child,
+        stderr: Arc<Mutex<VecDeque<String>>>,
+        reader: Option<JoinHandle<()>>,
+    }
+
+    impl PlayerProcess {
+        fn new(name: &str, mut child: Child) -> Self {
+            let stderr = Arc::new(Mutex::new(VecDeque::new()));
+            let reader = child.stderr.take().map(|pipe| {
+                let stderr = stderr.clone();
+                thread::spawn(move || {
+                    for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                        let mut stderr = stderr.lock().unwrap();
+                        if stderr.len() == STDERR_LINES {
+                            stderr.pop_front();
+                        }
+                        stderr.push_back(line);
+                    }
+                })
+            });
+            Self {
+                name: name.to_string(),
+                child,
+                stderr,
+                reader,
+            }
+        }
+
+        /// How the player ended, `None` while it is still running.
+        pub fn try_wait(&mut self) -> io::Result<Option<Outcome>> {
+            let Some(status) = self.child.try_wait()? else {
+                return Ok(None);
+            };
+            // the last lines may still be on their way through the pipe
+            if let Some(reader) = self.reader.take() {
+                reader.join().ok();
+            }
+            Ok(Some(self.outcome(status)))
+        }
+
+        /// Waits for the player to exit, along with everything it wrote to stderr.
+        pub fn wait(mut self) -> io::Result<Outcome> {
+            let status = self.child.wait()?;
+            if let Some(reader) = self.reader.take() {
+                reader.join().ok();
+            }
+            Ok(self.outcome(status))
+        }
+
+        pub fn kill(mut self) {
+            self.child.kill().ok();
+            self.child.wait().ok();
+        }
+
+        fn outcome(&self, status: ExitStatus) -> Outcome {
+            Outcome {
+                player: self.name.clone(),
+                status,
+                stderr: self.stderr.lock().unwrap().iter().cloned().collect(),
+            }
+        }
+    }
+
+    /// How a player process ended.
+    #[derive(Debug)]
+    pub struct Outcome {
+        pub player: String,
+        pub status: ExitStatus,
+        /// The last lines the player wrote to stderr.
+        pub stderr: Vec<String>,
+    }
+
+    impl Outcome {
+        pub fn success(&self) -> bool {
+            self.status.success()
+        }
+
+        /// The exit code to pass on, 1 when the player was killed by a signal.
+        pub fn code(&self) -> i32 {
+            self.status.code().unwrap_or(1)
+        }
+
+        /// The stderr line that most likely explains a failure.
+        pub fn reason(&self) -> Option<&str> {
+            let lines = || {
+                self.stderr
+                    .iter()
+                    .rev()
+                    .map(|l| l.trim())
+                    .filter(|l| !l.is_empty())
+            };
+            lines()
+                .find(|l| l.to_ascii_lowercase().contains("error"))
+                .or_else(|| lines().next())
+        }
+    }
+
+    impl fmt::Display for Outcome {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if self.success() {
+                return write!(f, "{} finished", self.player);
+            }
+            write!(f, "{} failed ({})", self.player, self.status)?;
+            if let Some(reason) = self.reason() {
+                write!(f, ": {}", reason)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// mpv, started with a JSON IPC socket so playback can be controlled.
+    pub struct Mpv {
+        ipc_socket: PathBuf,
+    }
+
+    impl Mpv {
+        pub fn new() -> Self {
+            Self {
+                ipc_socket: env::temp_dir().join(format!("yt-cli-mpv-{}.sock", process::id())),
+            }
+        }
+    }
+
+    impl Default for Mpv {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl Player for Mpv {
+        fn name(&self) -> &str {
+            "mpv"
+        }
+
+        fn binary(&self) -> &str {
+            "mpv"
+        }
+
+        fn command(&self, urls: &[String], options: &PlayOptions) -> Command {
+            let mut command = Command::new("mpv");
+            command.arg(format!("--input-ipc-server={}", self.ipc_socket.display()));
+            if options.audio_only {
+                // skip downloading the video track altogether
+                command.args(["--no-video", "--ytdl-format=bestaudio/best"]);
+            }
+            if let Some(audio_file) = &options.audio_file {
+                command.arg(format!("--audio-file={}", audio_file));
+            }
+            if let Some(title) = &options.title {
+                command.arg(format!("--force-media-title={}", title));
+            }
+            if options.direct {
+                command.arg("--ytdl=no");
+            }
+            if let Some(start) = options.start {
+                command.arg(format!("--start={}", start));
+            }
+            for sub_file in &options.sub_files {
+                command.arg(format!("--sub-file={}", sub_file.display()));
+            }
+            command.args(urls.iter().map(|url| url.trim()));
+            command
+        }
+
+        /// There is none where mpv listens on a named pipe, see [`crate::ipc`].
+        fn ipc_socket(&self) -> Option<&Path> {
+            cfg!(unix).then_some(&self.ipc_socket)
+        }
+    }
+
+    pub struct Vlc;
+
+    impl Player for Vlc {
+        fn name(&self) -> &str {
+            "vlc"
+        }
+
+        fn binary(&self) -> &str {
+            "vlc"
+        }
+
+        fn command(&self, urls: &[String], options: &PlayOptions) -> Command {
+            let mut command = Command::new("vlc");
+            command.arg("--play-and-exit");
+            if options.audio_only {
+                command.arg("--no-video");
+            }
+            if let Some(audio_file) = &options.audio_file {
+                command.arg(format!("--input-slave={}", audio_file));
+            }
+            if let Some(title) = &options.title {
+                command.arg(format!("--meta-title={}", title));
+            }
+            if let Some(start) = options.start {
+                command.arg(format!("--start-time={}", start));
+            }
+            if let Some(sub_file) = options.sub_files.first() {
+                command.arg(format!("--sub-file={}", sub_file.display()));
+            }
+            command.args(urls.iter().map(|url| url.trim()));
+            command
+        }
+    }
+
+    /// Any other player, from a template like `celluloid {url}`.
+    ///
+    /// The template is split on whitespace, the `{url}` argument is replaced by the urls
+    /// and they are appended at the end when there is none. `{audio}`, `{start}` and `{sub}`
+    /// are replaced by the separate audio track, the start position and each subtitle file,
+    /// arguments with one of them are left out when there is nothing to fill in. Audio-only
+    /// mode is left to the template, e.g. `mpv --no-video {url}`.
+    pub struct CustomPlayer {
+        template: Vec<String>,
+    }
+
+    impl CustomPlayer {
+        pub fn new(template: &str) -> Self {
+            Self {
+                template: template.split_whitespace().map(String::from).collect(),
+            }
+        }
+    }
+
+    impl Player for CustomPlayer {
+        fn name(&self) -> &str {
+            self.binary()
+        }
+
+        fn binary(&self) -> &str {
+            self.template.first().map_or("", |s| s.as_str())
+        }
+
+        fn command(&self, urls: &[String], options: &PlayOptions) -> Command {
+            let mut command = Command::new(self.binary());
+            let mut has_url = false;
+            for arg in self.template.iter().skip(1) {
+                if arg.contains("{url}") {
+                    has_url = true;
+                    command.args(urls.iter().map(|url| arg.replace("{url}", url.trim())));
+                } else if arg.contains("{audio}") {
+                    command.args(options.audio_file.iter().map(|a| arg.replace("{audio}", a)));
+                } else if arg.contains("{start}") {
+                    let start = options.start.map(|start| start.to_string());
+                    command.args(start.iter().map(|start| arg.replace("{start}", start)));
+                } else if arg.contains("{sub}") {
+                    let subs = options.sub_files.iter();
+                    command.args(subs.map(|sub| arg.replace("{sub}", &sub.display().to_string())));
+                } else {
+                    command.arg(arg);
+                }
+            }
+            if !has_url {
+                command.args(urls.iter().map(|url| url.trim()));
+            }
+            command
+        }
+
+        fn separate_audio(&self) -> bool {
+            self.template.iter().any(|arg| arg.contains("{audio}"))
+        }
+    }
+
+    /// `mpv` and `vlc` get their built-in support, anything else is a [`CustomPlayer`] template.
+    pub fn from_name(name: &str) -> Box<dyn Player> {
+        match name.trim() {
+            "mpv" => Box::new(Mpv::new()),
+            "vlc" => Box::new(Vlc),
+            template => Box::new(CustomPlayer::new(template)),
+        }
+    }
+
+    /// The built-in players found in `PATH`.
+    pub fn detect_installed() -> Vec<&'static str> {
+        BUILTIN
+            .iter()
+            .copied()
+            .filter(|name| is_in_path(name))
+            .collect()
+    }
+
+    fn is_in_path(binary: &str) -> bool {
+        if binary.is_empty() {
+            return false;
+        }
+        let Some(paths) = env::var_os("PATH") else {
+            return false;
+        };
+        env::split_paths(&paths).any(|dir| is_executable(&dir.join(binary)))
+    }
+
+    #[cfg(unix)]
+    fn is_executable(path: &Path) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+
+    /// `mpv` is `mpv.exe` on Windows, found with the extensions in `PATHEXT`.
+    #[cfg(not(unix))]
+    fn is_executable(path: &Path) -> bool {
+        let extensions = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+        path.is_file()
+            || extensions
+                .split(';')
+                .filter(|ext| !ext.is_empty())
+                .any(|ext| {
+                    let mut name = path.as_os_str().to_owned();
+                    name.push(ext);
+                    Path::new(&name).is_file()
+                })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        #[cfg(unix)]
+        use std::os::unix::process::ExitStatusExt;
+
+        #[cfg(unix)]
+        fn outcome(status: ExitStatus, stderr: &[&str]) -> Outcome {
+            Outcome {
+                player: "mpv".to_string(),
+                status,
+                stderr: stderr.iter().map(|l| l.to_string()).collect(),
+            }
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn reason_prefers_the_last_error_line() {
+            let failed = ExitStatus::from_raw(2 << 8);
+            let stderr = [
+                "error: first",
+                "Error: the one",
+                "Exiting... (Errors when loading file)",
+            ];
+            assert_eq!(
+                outcome(failed, &stderr).reason(),
+                Some("Exiting... (Errors when loading file)")
+            );
+            let stderr = ["[ytdl] ERROR: the one", "  done  ", "", "   "];
+            assert_eq!(
+                outcome(failed, &stderr).reason(),
+                Some("[ytdl] ERROR: the one")
+            );
+            // without an error line the last one that says anything
+            let stderr = ["one", " two ", "  "];
+            assert_eq!(outcome(failed, &stderr).reason(), Some("two"));
+            assert_eq!(outcome(failed, &["", " "]).reason(), None);
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn code_is_one_for_a_signal() {
+            assert_eq!(outcome(ExitStatus::from_raw(0), &[]).code(), 0);
+            assert_eq!(outcome(ExitStatus::from_raw(3 << 8), &[]).code(), 3);
+            // killed by SIGKILL
+            assert_eq!(outcome(ExitStatus::from_raw(9), &[]).code(), 1);
+        }
+
+        fn args(command: &Command) -> Vec<String> {
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        }
+
+        #[test]
+        fn custom_player_puts_the_urls_in_place_of_url() {
+            let urls = ["https://a/1 ".to_string(), "https://a/2".to_string()];
+            let options = PlayOptions::default();
+            let player = CustomPlayer::new("celluloid --new-window {url} --fullscreen");
+            assert_eq!(player.binary(), "celluloid");
+            assert_eq!(
+                args(&player.command(&urls, &options)),
+                ["--new-window", "https://a/1", "https://a/2", "--fullscreen"]
+            );
+            // part of an argument
+            let player = CustomPlayer::new("player --input={url}");
+            assert_eq!(
+                args(&player.command(&urls[..1], &options)),
+                ["--input=https://a/1"]
+            );
+            // appended without one
+            let player = CustomPlayer::new("  vlc   --fullscreen ");
+            assert_eq!(
+                args(&player.command(&urls, &options)),
+                ["--fullscreen", "https://a/1", "https://a/2"]
+            );
+            assert!(args(&CustomPlayer::new("celluloid").command(&[], &options)).is_empty());
+        }
+
+        #[test]
+        fn custom_player_fills_in_audio_start_and_sub() {
+            let player =
+                CustomPlayer::new("mpv --audio-file={audio} --start={start} --sub-file={sub}");
+            assert!(player.separate_audio());
+            let options = PlayOptions {
+                audio_file: Some("https://a/audio".to_string()),
+                start: Some(42.5),
+                sub_files: vec![PathBuf::from("/tmp/a.srt"), PathBuf::from("/tmp/b.srt")],
+                ..PlayOptions::default()
+            };
+            assert_eq!(
+                args(&player.command(&["https://a/video".to_string()], &options)),
+                [
+                    "--audio-file=https://a/audio",
+                    "--start=42.5",
+                    "--sub-file=/tmp/a.srt",
+                    "--sub-file=/tmp/b.srt",
+                    "https://a/video",
+                ]
+            );
+            // nothing to fill in leaves the arguments out
+            assert_eq!(
+                args(&player.command(&["https://a/video".to_string()], &PlayOptions::default())),
+                ["https://a/video"]
+            );
+            assert!(!CustomPlayer::new("celluloid {url}").separate_audio());
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn try_wait_has_all_of_stderr() {
+            // the "urls" end up as the arguments of sh
+            let player = CustomPlayer::new("sh");
+            let urls = [
+                "-c".to_string(),
+                "echo 'error: boom' >&2; exit 3".to_string(),
+            ];
+            for _ in 0..200 {
+                let mut process = player.spawn(&urls, &PlayOptions::default()).unwrap();
+                let outcome = loop {
+                    if let Some(outcome) = process.try_wait().unwrap() {
+                        break outcome;
+                    }
+                    thread::yield_now();
+                };
+                assert_eq!(outcome.code(), 3);
+                assert_eq!(outcome.reason(), Some("error: boom"));
+            }
+        }
+    }
+}
+
+pub mod ipc {
+    use serde::de::DeserializeOwned;
+    use serde_json::{json, Value};
+    use std::{
+        error::Error,
+        fmt,
+        io::{self, BufRead, BufReader, Read, Write},
+        path::Path,
+    };
+
+    #[cfg(unix)]
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+    #[derive(Debug)]
+    pub enum IpcError {
+        Io(io::Error),
+        Json(serde_json::Error),
+        /// mpv answered with an error, e.g. `property unavailable`.
+        Mpv(String),
+    }
+
+    impl fmt::Display for IpcError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                IpcError::Io(e) => write!(f, "mpv ipc: {}", e),
+                IpcError::Json(e) => write!(f, "mpv ipc: {}", e),
+                IpcError::Mpv(e) => write!(f, "mpv: {}", e),
+            }
+        }
+    }
+
+    impl Error for IpcError {}
+
+    impl From<io::Error> for IpcError {
+        fn from(e: io::Error) -> Self {
+            IpcError::Io(e)
+        }
+    }
+
+    impl From<serde_json::Error> for IpcError {
+        fn from(e: serde_json::Error) -> Self {
+            IpcError::Json(e)
+        }
+    }
+
+    /// What mpv is playing, as shown in the status block.
+    #[derive(Debug, Clone, Default)]
+    pub struct PlaybackStatus {
+        pub title: String,
+        /// Seconds into the file.
+        pub position: f64,
+        /// Seconds, zero while unknown.
+        pub duration: f64,
+        pub paused: bool,
+        pub volume: f64,
+        pub speed: f64,
+    }
+
+    /// A client for mpv's JSON IPC, see `--input-ipc-server`.
+    pub struct MpvIpc {
+        writer: Box<dyn Write + Send>,
+        reader: BufReader<Box<dyn Read + Send>>,
+        next_id: u64,
+    }
+
+    impl MpvIpc {
+        #[cfg(unix)]
+        pub fn connect(path: &Path) -> io::Result<Self> {
+            let writer = std::os::unix::net::UnixStream::connect(path)?;
+            writer.set_read_timeout(Some(TIMEOUT))?;
+            writer.set_write_timeout(Some(TIMEOUT))?;
+            let reader = BufReader::new(Box::new(writer.try_clone()?) as Box<dyn Read + Send>);
+            Ok(Self {
+                writer: Box::new(writer),
+                reader,
+                next_id: 1,
+            })
+        }
+
+        /// mpv listens on a named pipe elsewhere, which is not supported: the player runs
+        /// without controls.
+        #[cfg(not(unix))]
+        pub fn connect(_path: &Path) -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "mpv ipc needs unix sockets",
+            ))
+        }
+
+        /// Runs an mpv input command and returns its `data`.
+        pub fn command(&mut self, args: &[Value]) -> Result<Value, IpcError> {
+            let id = self.next_id;
+            self.next_id += 1;
+
+            let mut request = json!({ "command": args, "request_id": id }).to_string();
+            request.push('\n');
+            self.writer.write_all(request.as_bytes())?;
+
+            // events are interleaved with replies, skip until ours shows up
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if self.reader.read_line(&mut line)? == 0 {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                let reply: Value = serde_json::from_str(&line)?;
+                if reply.get("request_id").and_then(Value::as_u64) != Some(id) {
+                    continue;
+                }
+                return match reply.get("error").and_then(Value::as_str) {
+                    Some("success") => Ok(reply.get("data").cloned().unwrap_or(Value::Null)),
+                    Some(error) => Err(IpcError::Mpv(error.to_string())),
+                    None => Err(IpcError::Mpv("malformed reply".to_string())),
+                };
+            }
+        }
+
+        pub fn get_property<T: DeserializeOwned>(&mut self, name: &str) -> Result<T, IpcError> {
+            let data = self.command(&[json!("get_property"), json!(name)])?;
+            Ok(serde_json::from_value(data)?)
+        }
+
+        pub fn set_property(&mut self, name: &str, value: Value) -> Result<(), IpcError> {
+            self.command(&[json!("set_property"), json!(name), value])
+                .map(|_| ())
+        }
+
+        pub fn toggle_pause(&mut self) -> Result<(), IpcError> {
+            self.command(&[json!("cycle"), json!("pause")]).map(|_| ())
+        }
+
+        /// Seeks relative to the current position.
+        pub fn seek(&mut self, seconds: f64) -> Result<(), IpcError> {
+            self.command(&[json!("seek"), json!(seconds), json!("relative")])
+                .map(|_| ())
+        }
+
+        pub fn add_volume(&mut self, delta: f64) -> Result<(), IpcError> {
+            self.command(&[json!("add"), json!("volume"), json!(delta)])
+                .map(|_| ())
+        }
+
+        pub fn multiply_speed(&mut self, factor: f64) -> Result<(), IpcError> {
+            self.command(&[json!("multiply"), json!("speed"), json!(factor)])
+                .map(|_| ())
+        }
+
+        /// Quits mpv.
+        pub fn stop(&mut self) -> Result<(), IpcError> {
+            self.command(&[json!("quit")]).map(|_| ())
+        }
+
+        pub fn status(&mut self) -> Result<PlaybackStatus, IpcError> {
+            // most properties are unavailable until the file has loaded
+            let optional = |r: Result<f64, IpcError>| match r {
+                Err(IpcError::Mpv(_)) => Ok(0.0),
+                r => r,
+            };
+            Ok(PlaybackStatus {
+                title: self.get_property("media-title").unwrap_or_default(),
+                position: optional(self.get_property("time-pos"))?,
+                duration: optional(self.get_property("duration"))?,
+                paused: self.get_property("pause")?,
+                volume: optional(self.get_property("volume"))?,
+                speed: optional(self.get_property("speed"))?,
+            })
+        }
+    }
+
+    #[cfg(all(test, unix))]
+    mod tests {
+        use super::*;
+        use std::{
+            env, fs,
+            os::unix::net::UnixListener,
+            path::PathBuf,
+            process,
+            sync::{Arc, Mutex},
+            thread,
+        };
+
+        type Commands = Arc<Mutex<Vec<Value>>>;
+
+        /// A fake mpv that answers every command with `reply`, after an event and a reply
+        /// to someone else's request.
+        fn serve(name: &str, reply: fn(&[Value]) -> Value) -> (PathBuf, Commands) {
+            let path = env::temp_dir().join(format!("yt-cli-ipc-{}-{}", process::id(), name));
+            fs::remove_file(&path).ok();
+            let listener = UnixListener::bind(&path).unwrap();
+            let commands = Commands::default();
+            let received = commands.clone();
+            thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                let mut writer = stream.try_clone().unwrap();
+                for line in BufReader::new(stream).lines() {
+                    let request: Value = serde_json::from_str(&line.unwrap()).unwrap();
+                    let id = request["request_id"].as_u64().unwrap();
+                    let command = request["command"].as_array().unwrap().clone();
+                    let mut answer = reply(&command);
+                    answer["request_id"] = json!(id);
+                    received.lock().unwrap().push(Value::Array(command));
+
+                    let other =
+                        json!({ "request_id": id + 100, "error": "success", "data": "other" });
+                    for line in [json!({ "event": "playback-restart" }), other, answer] {
+                        writeln!(writer, "{}", line).unwrap();
+                    }
+                }
+            });
+            (path, commands)
+        }
+
+        fn property(command: &[Value]) -> Value {
+            match command[1].as_str().unwrap() {
+                "media-title" => json!({ "error": "success", "data": "A video" }),
+                "pause" => json!({ "error": "success", "data": true }),
+                "volume" => json!({ "error": "success", "data": 80.0 }),
+                "speed" => json!({ "error": "success", "data": 1.5 }),
+                _ => json!({ "error": "property unavailable" }),
+            }
+        }
+
+        #[test]
+        fn command_sends_arguments_and_returns_data() {
+            let (path, commands) = serve("command", |_| json!({ "error": "success" }));
+            let mut ipc = MpvIpc::connect(&path).unwrap();
+            ipc.seek(-5.0).unwrap();
+            ipc.toggle_pause().unwrap();
+            assert_eq!(
+                *commands.lock().unwrap(),
+                [json!(["seek", -5.0, "relative"]), json!(["cycle", "pause"])]
+            );
+            fs::remove_file(path).ok();
+        }
+
+        #[test]
+        fn get_property_skips_events_and_other_replies() {
+            let (path, _) = serve("get-property", property);
+            let mut ipc = MpvIpc::connect(&path).unwrap();
+            assert_eq!(ipc.get_property::<f64>("volume").unwrap(), 80.0);
+            assert_eq!(
+                ipc.get_property::<String>("media-title").unwrap(),
+                "A video"
+            );
+            fs::remove_file(path).ok();
+        }
+
+        #[test]
+        fn mpv_errors_are_reported() {
+            let (path, _) = serve("error", property);
+            let mut ipc = MpvIpc::connect(&path).unwrap();
+            match ipc.get_property::<f64>("time-pos") {
+                Err(IpcError::Mpv(e)) => assert_eq!(e, "property unavailable"),
+                other => panic!("expected an mpv error, got {:?}", other),
+            }
+            fs::remove_file(path).ok();
+        }
+
+        #[test]
+        fn status_uses_zero_for_unavailable_properties() {
+            let (path, _) = serve("status", property);
+            let mut ipc = MpvIpc::connect(&path).unwrap();
+            let status = ipc.status().unwrap();
+            assert_eq!(status.title, "A video");
+            assert_eq!(status.position, 0.0);
+            assert_eq!(status.duration, 0.0);
+            assert!(status.paused);
+            assert_eq!(status.volume, 80.0);
+            assert_eq!(status.speed, 1.5);
+            fs::remove_file(path).ok();
+        }
+    }
+}
+
+pub mod queue {
+    use crate::backend::OrangeResult;
+    use std::collections::VecDeque;
+
+    /// A video waiting to be played.
+    #[derive(Debug, Clone)]
+    pub struct QueueItem {
+        pub id: String,
+        pub title: String,
+        pub uploader: String,
+        pub duration: String,
+    }
+
+    impl From<&OrangeResult> for QueueItem {
+        fn from(r: &OrangeResult) -> Self {
+            Self {
+                id: r.id.clone(),
+                title: r.title.clone(),
+                uploader: r.uploader.clone(),
+                duration: r.duration.clone(),
+            }
+        }
+    }
+
+    /// Videos played one after another, each once the previous one has ended.
+    #[derive(Debug, Default)]
+    pub struct Queue {
+        items: VecDeque<QueueItem>,
+    }
+
+    impl Queue {
+        pub fn push(&mut self, item: QueueItem) {
+            self.items.push_back(item);
+        }
+
+        /// Puts `items` ahead of everything queued, in their order.
+        pub fn push_front(&mut self, items: impl IntoIterator<Item = QueueItem>) {
+            let queued = std::mem::take(&mut self.items);
+            self.items.extend(items);
+            self.items.extend(queued);
+        }
+
+        /// Takes the next item to play.
+        pub fn pop(&mut self) -> Option<QueueItem> {
+            self.items.pop_front()
+        }
+
+        pub fn remove(&mut self, i: usize) -> Option<QueueItem> {
+            self.items.remove(i)
+        }
+
+        /// Moves the item at `i` one place towards the front, returns false when it can't.
+        pub fn move_up(&mut self, i: usize) -> bool {
+            if i == 0 || i >= self.items.len() {
+                return false;
+            }
+            self.items.swap(i - 1, i);
+            true
+        }
+
+        /// Moves the item at `i` one place towards the back, returns false when it can't.
+        pub fn move_down(&mut self, i: usize) -> bool {
+            if i + 1 >= self.items.len() {
+                return false;
+            }
+            self.items.swap(i, i + 1);
+            true
+        }
+
+        pub fn clear(&mut self) {
+            self.items.clear();
+        }
+
+        pub fn len(&self) -> usize {
+            self.items.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.items.is_empty()
+        }
+
+        pub fn iter(&self) -> impl Iterator<Item = &QueueItem> {
+            self.items.iter()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn queue(ids: &[&str]) -> Queue {
+            let mut queue = Queue::default();
+            for id in ids {
+                queue.push(QueueItem {
+                    id: id.to_string(),
+                    title: format!("Video {}", id),
+                    uploader: String::new(),
+                    duration: String::new(),
+                });
+            }
+            queue
+        }
+
+        fn ids(queue: &Queue) -> Vec<&str> {
+            queue.iter().map(|item| item.id.as_str()).collect()
+        }
+
+        #[test]
+        fn pops_in_order() {
+            let mut queue = queue(&["a", "b"]);
+            assert_eq!(queue.pop().unwrap().id, "a");
+            assert_eq!(queue.pop().unwrap().id, "b");
+            assert!(queue.pop().is_none());
+        }
+
+        #[test]
+        fn move_up_stops_at_the_front() {
+            let mut queue = queue(&["a", "b", "c"]);
+            assert!(queue.move_up(2));
+            assert_eq!(ids(&queue), ["a", "c", "b"]);
+            assert!(queue.move_up(1));
+            assert_eq!(ids(&queue), ["c", "a", "b"]);
+            assert!(!queue.move_up(0));
+            assert!(!queue.move_up(3));
+            assert_eq!(ids(&queue), ["c", "a", "b"]);
+        }
+
+        #[test]
+        fn move_down_stops_at_the_back() {
+            let mut queue = queue(&["a", "b", "c"]);
+            assert!(queue.move_down(0));
+            assert_eq!(ids(&queue), ["b", "a", "c"]);
+            assert!(queue.move_down(1));
+            assert_eq!(ids(&queue), ["b", "c", "a"]);
+            assert!(!queue.move_down(2));
+            assert!(!queue.move_down(3));
+            assert!(!Queue::default().move_down(0));
+            assert_eq!(ids(&queue), ["b", "c", "a"]);
+        }
+
+        #[test]
+        fn remove_ignores_an_index_past_the_end() {
+            let mut queue = queue(&["a", "b", "c"]);
+            assert_eq!(queue.remove(1).unwrap().id, "b");
+            assert!(queue.remove(2).is_none());
+            assert_eq!(queue.remove(1).unwrap().id, "c");
+            assert_eq!(queue.remove(0).unwrap().id, "a");
+            assert!(queue.remove(0).is_none());
+            assert!(queue.is_empty());
+        }
+
+        #[test]
+        fn push_front_goes_ahead_of_the_queued() {
+            let front: Vec<QueueItem> = queue(&["x", "y"]).iter().cloned().collect();
+            let mut queue = queue(&["a", "b"]);
+            queue.push_front(front);
+            assert_eq!(ids(&queue), ["x", "y", "a", "b"]);
+        }
+    }
+}
+
+pub mod cache {
+    use crate::backend::OrangeResult;
+    use std::{error::Error, fs, io, path::PathBuf};
+
+    /// The last trending page of `region`, kept in `$XDG_CACHE_HOME/yt-cli/` so the TUI
+    /// has something to list before the network answers, or when it never does.
+    pub fn trending_path(region: &str) -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| {
+            dir.join("yt-cli")
+                .join(format!("trending-{}.json", region.to_lowercase()))
+        })
+    }
+
+    /// The cached trending page of `region`, empty when there is none yet.
+    pub fn load_trending(region: &str) -> Result<Vec<OrangeResult>, Box<dyn Error>> {
+        match trending_path(region) {
+            Some(path) if path.exists() => {
+                let body = fs::read_to_string(&path)?;
+                Ok(
+                    serde_json::from_str(&body)
+                        .map_err(|e| format!("{}: {}", path.display(), e))?,
+                )
+            }
+            _ => Ok(vec![]),
+        }
+    }
+
+    pub fn save_trending(region: &str, results: &[OrangeResult]) -> io::Result<()> {
+        let Some(path) = trending_path(region) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_string(results)?)
+    }
+}
+
+pub mod positions {
+    use serde::{Deserialize, Serialize};
+    use std::{
+        collections::HashMap,
+        error::Error,
+        fs, io,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    /// Positions this close to the start are not worth resuming.
+    const MIN_POSITION: f64 = 10.0;
+    /// Positions this close to the end count as watched.
+    const END_MARGIN: f64 = 15.0;
+
+    /// Where a video was left off, in seconds.
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+    pub struct Position {
+        pub position: f64,
+        pub duration: f64,
+        /// Unix time of the last update.
+        pub updated: u64,
+    }
+
+    impl Position {
+        /// How much of the video was watched, from 0 to 1.
+        pub fn progress(&self) -> f64 {
+            if self.duration > 0.0 {
+                (self.position / self.duration).clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        }
+    }
+
+    /// Playback positions by video id, kept in `$XDG_DATA_HOME/yt-cli/positions.json`.
+    #[derive(Debug, Default)]
+    pub struct Positions {
+        positions: HashMap<String, Position>,
+        dirty: bool,
+    }
+
+    impl Positions {
+        pub fn path() -> Option<PathBuf> {
+            dirs::data_dir().map(|dir| dir.join("yt-cli").join("positions.json"))
+        }
+
+        /// Loads the saved positions, none when the file does not exist yet.
+        pub fn load() -> Result<Positions, Box<dyn Error>> {
+            let positions = match Self::path() {
+                Some(path) if path.exists() => {
+                    let body = fs::read_to_string(&path)?;
+                    serde_json::from_str(&body).map_err(|e| format!("{}: {}", path.display(), e))?
+                }
+                _ => HashMap::new(),
+            };
+            Ok(Positions {
+                positions,
+                dirty: false,
+            })
+        }
+
+        /// Writes the positions back when they changed since the last save.
+        pub fn save(&mut self) -> io::Result<()> {
+            if !self.dirty {
+                return Ok(());
+            }
+            let Some(path) = Self::path() else {
+                return Ok(());
+            };
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&path, serde_json::to_string(&self.positions)?)?;
+            self.dirty = false;
+            Ok(())
+        }
+
+        /// The position to resume `id` from, if it was left partly watched.
+        pub fn get(&self, id: &str) -> Option<Position> {
+            self.positions.get(id).copied()
+        }
+
+        /// Records how far `id` got, forgetting it once it is watched to the end.
+        pub fn update(&mut self, id: &str, position: f64, duration: f64) {
+            if duration <= 0.0 {
+                return;
+            }
+            if position < MIN_POSITION || position > duration - END_MARGIN {
+                self.remove(id);
+                return;
+            }
+            self.dirty = true;
+            let updated = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            self.positions.insert(
+                id.to_string(),
+                Position {
+                    position,
+                    duration,
+                    updated,
+                },
+            );
+        }
+
+        pub fn remove(&mut self, id: &str) {
+            self.dirty |= self.positions.remove(id).is_some();
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn keeps_positions_worth_resuming() {
+            let mut positions = Positions::default();
+            positions.update("a", 60.0, 600.0);
+            assert!(positions.dirty);
+            let position = positions.get("a").unwrap();
+            assert_eq!((position.position, position.duration), (60.0, 600.0));
+            assert_eq!(position.progress(), 0.1);
+            // the margins themselves still count
+            positions.update("b", MIN_POSITION, 600.0);
+            positions.update("c", 600.0 - END_MARGIN, 600.0);
+            assert!(positions.get("b").is_some());
+            assert!(positions.get("c").is_some());
+        }
+
+        #[test]
+        fn forgets_positions_near_the_start_or_end() {
+            let mut positions = Positions::default();
+            positions.update("a", 60.0, 600.0);
+            positions.update("a", 5.0, 600.0);
+            assert!(positions.get("a").is_none());
+            positions.update("b", 60.0, 600.0);
+            positions.update("b", 590.0, 600.0);
+            assert!(positions.get("b").is_none());
+            // shorter than both margins together
+            positions.update("c", 12.0, 20.0);
+            assert!(positions.get("c").is_none());
+        }
+
+        #[test]
+        fn ignores_an_unknown_duration() {
+            let mut positions = Positions::default();
+            positions.update("a", 60.0, 0.0);
+            assert!(positions.get("a").is_none());
+            assert!(!positions.dirty);
+            positions.update("b", 60.0, 600.0);
+            positions.dirty = false;
+            positions.update("b", 120.0, 0.0);
+            assert_eq!(positions.get("b").unwrap().position, 60.0);
+            assert!(!positions.dirty);
+        }
+
+        #[test]
+        fn changes_only_mark_it_dirty() {
+            let mut positions = Positions::default();
+            // nothing was stored for these
+            positions.update("a", 5.0, 600.0);
+            positions.update("a", 595.0, 600.0);
+            positions.remove("a");
+            assert!(!positions.dirty);
+            positions.update("a", 60.0, 600.0);
+            positions.dirty = false;
+            positions.update("a", 595.0, 600.0);
+            assert!(positions.dirty);
+        }
+
+        #[test]
+        fn progress_stays_between_zero_and_one() {
+            let position = |position, duration| Position {
+                position,
+                duration,
+                updated: 0,
+            };
+            assert_eq!(position(30.0, 120.0).progress(), 0.25);
+            assert_eq!(position(200.0, 120.0).progress(), 1.0);
+            assert_eq!(position(30.0, 0.0).progress(), 0.0);
+        }
+    }
+}
+
+pub mod download {
+    use crate::backend::{self, QualityPreference, Stream, VideoDetails, USR_AGENT};
+    use reqwest::{header::RANGE, Client, StatusCode};
+    use std::{
+        collections::HashSet,
+        error::Error,
+        fmt, io,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    };
+    use tokio::{fs, io::AsyncWriteExt, sync::Semaphore};
+
+    pub const DEFAULT_TEMPLATE: &str = "{title} [{id}].{ext}";
+    pub const DEFAULT_CONCURRENCY: usize = 3;
+
+    #[derive(Debug)]
+    pub enum DownloadError {
+        Network(reqwest::Error),
+        Status(StatusCode),
+        Io(io::Error),
+        /// Another download writes to the same file.
+        InProgress(PathBuf),
+    }
+
+    impl fmt::Display for DownloadError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                DownloadError::Network(e) => write!(f, "network error: {}", e),
+                DownloadError::Status(status) => write!(f, "server answered {}", status),
+                DownloadError::Io(e) => write!(f, "{}", e),
+                DownloadError::InProgress(path) => {
+                    write!(f, "{} is already downloading", path.display())
+                }
+            }
+        }
+    }
+
+    impl Error for DownloadError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                DownloadError::Network(e) => Some(e),
+                DownloadError::Io(e) => Some(e),
+                DownloadError::Status(_) | DownloadError::InProgress(_) => None,
+            }
+        }
+    }
+
+    impl From<reqwest::Error> for DownloadError {
+        fn from(e: reqwest::Error) -> Self {
+            DownloadError::Network(e)
+        }
+    }
+
+    impl From<io::Error> for DownloadError {
+        fn from(e: io::Error) -> Self {
+            DownloadError::Io(e)
+        }
+    }
+
+    /// One file to download.
+    #[derive(Debug, Clone)]
+    pub struct DownloadJob {
+        pub url: String,
+        pub path: PathBuf,
+    }
+
+    /// Saves streams to disk, a limited number at a time.
+    #[derive(Clone)]
+    pub struct Downloader {
+        client: Client,
+        dir: PathBuf,
+        template: String,
+        permits: Arc<Semaphore>,
+        /// The paths being downloaded to, shared by all clones.
+        active: Arc<Mutex<HashSet<PathBuf>>>,
+    }
+
+    /// Releases a path of [`Downloader::active`] when its download ends or is dropped.
+    struct Active<'a> {
+        paths: &'a Mutex<HashSet<PathBuf>>,
+        path: PathBuf,
+    }
+
+    impl Drop for Active<'_> {
+        fn drop(&mut self) {
+            self.paths.lock().unwrap().remove(&self.path);
+        }
+    }
+
+    impl Downloader {
+        /// `template` names the files, see [`file_name`].
+        pub fn new(dir: PathBuf, template: String, concurrency: usize) -> Self {
+            let client = Client::builder()
+                .user_agent(USR_AGENT)
+                .build()
+                .expect("Failed to build the http client");
+            Self {
+                client,
+                dir,
+                template,
+                permits: Arc::new(Semaphore::new(concurrency.max(1))),
+                active: Arc::default(),
+            }
+        }
+
+        pub fn dir(&self) -> &Path {
+            &self.dir
+        }
+
+        /// Where `stream` of the video ends up.
+        pub fn path_for(&self, details: &VideoDetails, stream: &Stream) -> PathBuf {
+            self.dir.join(file_name(&self.template, details, stream))
+        }
+
+        /// The files a download of the video is made of: the best audio track alone, or the
+        /// picked video stream with the audio track next to it when the stream has none.
+        pub fn jobs(
+            &self,
+            details: &VideoDetails,
+            quality: &QualityPreference,
+            audio_only: bool,
+        ) -> Vec<DownloadJob> {
+            let audio = backend::best_audio(&details.audio_streams);
+            let video = quality.pick(&details.video_streams);
+            match (video, audio) {
+                (Some(video), audio) if !audio_only => {
+                    let path = self.path_for(details, video);
+                    let mut jobs = vec![DownloadJob {
+                        url: video.url.clone(),
+                        path: path.clone(),
+                    }];
+                    if let Some(audio) = audio.filter(|_| video.video_only) {
+                        jobs.push(DownloadJob {
+                            url: audio.url.clone(),
+                            path: audio_path(&path, audio),
+                        });
+                    }
+                    jobs
+                }
+                (_, Some(audio)) => vec![DownloadJob {
+                    url: audio.url.clone(),
+                    path: self.path_for(details, audio),
+                }],
+                (_, None) => vec![],
+            }
+        }
+
+        /// Downloads `url` to `path`, picking up a `.part` file left by an interrupted run.
+        /// Fails with [`DownloadError::InProgress`] while another download writes to `path`.
+        ///
+        /// `progress` gets the bytes written so far and the total size when the server tells.
+        pub async fn download(
+            &self,
+            url: &str,
+            path: &Path,
+            mut progress: impl FnMut(u64, Option<u64>),
+        ) -> Result<(), DownloadError> {
+            // two writers would both append to the part file
+            if !self.active.lock().unwrap().insert(path.to_path_buf()) {
+                return Err(DownloadError::InProgress(path.to_path_buf()));
+            }
+            let _active = Active {
+                paths: &self.active,
+                path: path.to_path_buf(),
+            };
+            let _permit = self.permits.acquire().await.expect("semaphore closed");
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).await?;
+            }
+            let part = part_path(path);
+            let mut offset = fs::metadata(&part).await.map_or(0, |m| m.len());
+
+            let mut request = self.client.get(url);
+            if offset > 0 {
+                request = request.header(RANGE, format!("bytes={}-", offset));
+            }
+            let mut response = request.send().await?;
+            match response.status() {
+                StatusCode::PARTIAL_CONTENT => {}
+                // the part file is already complete
+                StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                    fs::rename(&part, path).await?;
+                    return Ok(());
+                }
+                // the server ignored the range, start over
+                status if status.is_success() => offset = 0,
+                status => return Err(DownloadError::Status(status)),
+            }
+
+            let total = response.content_length().map(|len| len + offset);
+            let mut file = if offset > 0 {
+                fs::OpenOptions::new().append(true).open(&part).await?
+            } else {
+                fs::File::create(&part).await?
+            };
+            let mut downloaded = offset;
+            progress(downloaded, total);
+            while let Some(chunk) = response.chunk().await? {
+                file.write_all(&chunk).await?;
+                downloaded += chunk.len() as u64;
+                progress(downloaded, total);
+            }
+            file.flush().await?;
+            drop(file);
+
+            fs::rename(&part, path).await?;
+            Ok(())
+        }
+    }
+
+    /// Fills in `{title}`, `{id}`, `{uploader}`, `{quality}` and `{ext}`, path separators in
+    /// the values are replaced so everything stays in one directory.
+    pub fn file_name(template: &str, details: &VideoDetails, stream: &Stream) -> String {
+        let clean = |s: &str| s.replace(['/', '\\', '\0'], "_");
+        template
+            .replace("{title}", &clean(&details.title))
+            .replace("{id}", &clean(&details.id))
+            .replace("{uploader}", &clean(&details.uploader))
+            .replace("{quality}", &clean(&stream.quality))
+            .replace("{ext}", extension(stream))
+    }
+
+    /// The file extension for the container Piped reports.
+    pub fn extension(stream: &Stream) -> &'static str {
+        match stream.format.as_str() {
+            "MPEG_4" => "mp4",
+            "M4A" => "m4a",
+            "WEBM" | "WEBMA" | "WEBMA_OPUS" => "webm",
+            "v3GPP" => "3gp",
+            _ if stream.mime_type.starts_with("audio/mp4") => "m4a",
+            _ if stream.mime_type.contains("mp4") => "mp4",
+            _ if stream.mime_type.contains("webm") => "webm",
+            _ => "bin",
+        }
+    }
+
+    /// The audio track saved next to a video-only stream, `name.audio.ext`.
+    pub fn audio_path(video_path: &Path, audio: &Stream) -> PathBuf {
+        let stem = video_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        video_path.with_file_name(format!("{}.audio.{}", stem, extension(audio)))
+    }
+
+    fn part_path(path: &Path) -> PathBuf {
+        let mut part = path.as_os_str().to_owned();
+        part.push(".part");
+        PathBuf::from(part)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+        use std::{
+            env, process,
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Mutex,
+            },
+        };
+        use tokio::{
+            io::{AsyncBufReadExt, BufReader},
+            net::TcpListener,
+        };
+
+        const BODY: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+        /// How the test server answers.
+        #[derive(Clone, Copy)]
+        enum Server {
+            /// Honours `Range`, 416 past the end.
+            Ranges,
+            /// Always sends the whole body with 200.
+            IgnoresRange,
+            Fails(u16),
+        }
+
+        /// Serves [`BODY`] on localhost, returns its url and the `Range` header of every
+        /// request.
+        async fn serve(server: Server) -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/stream", listener.local_addr().unwrap());
+            let ranges = Arc::new(Mutex::new(vec![]));
+            let received = ranges.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let (reader, mut writer) = stream.into_split();
+                    let mut lines = BufReader::new(reader).lines();
+                    let mut range = None;
+                    while let Some(line) = lines.next_line().await.unwrap() {
+                        if line.is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("range") {
+                                range = Some(value.trim().to_string());
+                            }
+                        }
+                    }
+                    received.lock().unwrap().push(range.clone());
+
+                    let start = range
+                        .as_deref()
+                        .and_then(|r| r.strip_prefix("bytes="))
+                        .and_then(|r| r.trim_end_matches('-').parse::<usize>().ok());
+                    let (status, body) = match (server, start) {
+                        (Server::Fails(status), _) => (status, &[][..]),
+                        (Server::Ranges, Some(start)) if start >= BODY.len() => (416, &[][..]),
+                        (Server::Ranges, Some(start)) => (206, &BODY[start..]),
+                        _ => (200, BODY),
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len()
+                    );
+                    writer.write_all(head.as_bytes()).await.unwrap();
+                    writer.write_all(body).await.unwrap();
+                    writer.shutdown().await.ok();
+                }
+            });
+            (url, ranges)
+        }
+
+        /// A fresh directory for one test.
+        fn temp_dir() -> PathBuf {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let dir = env::temp_dir().join(format!(
+                "yt-cli-download-{}-{}",
+                process::id(),
+                COUNT.fetch_add(1, Ordering::SeqCst)
+            ));
+            std::fs::remove_dir_all(&dir).ok();
+            std::fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        fn downloader(dir: &Path) -> Downloader {
+            Downloader::new(dir.to_path_buf(), DEFAULT_TEMPLATE.to_string(), 2)
+        }
+
+        #[tokio::test]
+        async fn downloads_a_fresh_file() {
+            let (url, ranges) = serve(Server::Ranges).await;
+            let dir = temp_dir();
+            let path = dir.join("video.mp4");
+            let mut last = (0, None);
+            downloader(&dir)
+                .download(&url, &path, |done, total| last = (done, total))
+                .await
+                .unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), BODY);
+            assert!(!part_path(&path).exists());
+            assert_eq!(last, (BODY.len() as u64, Some(BODY.len() as u64)));
+            assert_eq!(*ranges.lock().unwrap(), [None]);
+        }
+
+        #[tokio::test]
+        async fn refuses_a_second_download_to_the_same_path() {
+            let (url, ranges) = serve(Server::Ranges).await;
+            let dir = temp_dir();
+            let path = dir.join("video.mp4");
+            let downloader = downloader(&dir);
+            let other = downloader.clone();
+            let (first, second) = tokio::join!(
+                downloader.download(&url, &path, |_, _| {}),
+                other.download(&url, &path, |_, _| {}),
+            );
+            first.unwrap();
+            assert!(matches!(second, Err(DownloadError::InProgress(p)) if p == path));
+            assert_eq!(std::fs::read(&path).unwrap(), BODY);
+            assert_eq!(ranges.lock().unwrap().len(), 1);
+
+            // the path is free again once the download is done
+            std::fs::remove_file(&path).unwrap();
+            other.download(&url, &path, |_, _| {}).await.unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), BODY);
+        }
+
+        #[tokio::test]
+        async fn resumes_from_a_part_file() {
+            let (url, ranges) = serve(Server::Ranges).await;
+            let dir = temp_dir();
+            let path = dir.join("video.mp4");
+            std::fs::write(part_path(&path), &BODY[..10]).unwrap();
+            let mut first = None;
+            downloader(&dir)
+                .download(&url, &path, |done, total| {
+                    first.get_or_insert((done, total));
+                })
+                .await
+                .unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), BODY);
+            assert_eq!(first, Some((10, Some(BODY.len() as u64))));
+            assert_eq!(*ranges.lock().unwrap(), [Some("bytes=10-".to_string())]);
+        }
+
+        #[tokio::test]
+        async fn starts_over_when_the_range_is_ignored() {
+            let (url, _) = serve(Server::IgnoresRange).await;
+            let dir = temp_dir();
+            let path = dir.join("video.mp4");
+            // longer than the body, what is left of it must not survive
+            std::fs::write(part_path(&path), [b'x'; 100]).unwrap();
+            downloader(&dir)
+                .download(&url, &path, |_, _| {})
+                .await
+                .unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), BODY);
+        }
+
+        #[tokio::test]
+        async fn finishes_a_complete_part_file() {
+            let (url, ranges) = serve(Server::Ranges).await;
+            let dir = temp_dir();
+            let path = dir.join("video.mp4");
+            std::fs::write(part_path(&path), BODY).unwrap();
+            downloader(&dir)
+                .download(&url, &path, |_, _| {})
+                .await
+                .unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), BODY);
+            assert!(!part_path(&path).exists());
+            assert_eq!(
+                *ranges.lock().unwrap(),
+                [Some(format!("bytes={}-", BODY.len()))]
+            );
+        }
+
+        #[tokio::test]
+        async fn reports_error_statuses() {
+            let (url, _) = serve(Server::Fails(403)).await;
+            let dir = temp_dir();
+            let path = dir.join("video.mp4");
+            match downloader(&dir).download(&url, &path, |_, _| {}).await {
+                Err(DownloadError::Status(status)) => assert_eq!(status, StatusCode::FORBIDDEN),
+                other => panic!("expected a status error, got {:?}", other),
+            }
+            assert!(!path.exists());
+        }
+
+        fn stream(format: &str, mime_type: &str) -> Stream {
+            serde_json::from_value(json!({
+                "url": "https://proxy.example/stream",
+                "format": format,
+                "quality": "1080p",
+                "mimeType": mime_type,
+            }))
+            .unwrap()
+        }
+
+        fn details(title: &str) -> VideoDetails {
+            VideoDetails {
+                id: "dQw4w9WgXcQ".to_string(),
+                title: title.to_string(),
+                description: String::new(),
+                upload_date: None,
+                uploader: "AC/DC".to_string(),
+                uploader_url: None,
+                uploader_verified: false,
+                duration: 0,
+                views: 0,
+                likes: 0,
+                dislikes: 0,
+                chapters: vec![],
+                related_streams: vec![],
+                audio_streams: vec![],
+                video_streams: vec![],
+                hls: None,
+                dash: None,
+                subtitles: vec![],
+            }
+        }
+
+        #[test]
+        fn file_name_fills_the_template() {
+            let stream = stream("MPEG_4", "video/mp4");
+            assert_eq!(
+                file_name(DEFAULT_TEMPLATE, &details("A video"), &stream),
+                "A video [dQw4w9WgXcQ].mp4"
+            );
+            assert_eq!(
+                file_name(
+                    "{uploader} - {title} {quality}.{ext}",
+                    &details("Up/Down"),
+                    &stream
+                ),
+                "AC_DC - Up_Down 1080p.mp4"
+            );
+        }
+
+        #[test]
+        fn audio_path_sits_next_to_the_video() {
+            let video = Path::new("/videos/A video [id].mp4");
+            assert_eq!(
+                audio_path(video, &stream("M4A", "audio/mp4")),
+                Path::new("/videos/A video [id].audio.m4a")
+            );
+            assert_eq!(
+                audio_path(video, &stream("", "audio/webm; codecs=\"opus\"")),
+                Path::new("/videos/A video [id].audio.webm")
+            );
+        }
+    }
+}
+
+pub mod thumbnails {
+    use crate::backend::{BackendError, PipedClient};
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use image::{imageops::FilterType, ImageError, ImageOutputFormat, RgbImage};
+    use std::{
+        env,
+        error::Error,
+        fmt::{self, Write},
+        fs,
+        io::{self, Cursor},
+        path::PathBuf,
+        str::FromStr,
+    };
+
+    /// Thumbnails are scaled down to this width once, nothing draws them bigger.
+    pub const WIDTH: u32 = 480;
+    /// Removes every image placed with the kitty graphics protocol.
+    pub const KITTY_CLEAR: &str = "\x1b_Ga=d,q=2\x1b\\";
+    /// Largest payload of one kitty graphics escape.
+    const KITTY_CHUNK: usize = 4096;
+    /// Levels per channel of the sixel palette, a 6x6x6 color cube.
+    const SIXEL_LEVELS: u32 = 6;
+
+    /// How images get onto the terminal.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Graphics {
+        /// The kitty graphics protocol, also spoken by WezTerm and Ghostty.
+        Kitty,
+        Sixel,
+        /// Two pixels per cell drawn with `▀`, works wherever true color does.
+        HalfBlocks,
+        Off,
+    }
+
+    impl Graphics {
+        /// Guesses what the terminal supports from the environment it sets.
+        pub fn detect() -> Graphics {
+            let var = |name| env::var(name).unwrap_or_default();
+            let term = var("TERM");
+            let program = var("TERM_PROGRAM");
+            // tmux and screen swallow the escapes unless told to pass them through
+            if env::var_os("TMUX").is_some() || term.starts_with("screen") {
+                return Graphics::HalfBlocks;
+            }
+            if env::var_os("KITTY_WINDOW_ID").is_some()
+                || matches!(term.as_str(), "xterm-kitty" | "xterm-ghostty")
+                || matches!(program.as_str(), "WezTerm" | "ghostty")
+            {
+                Graphics::Kitty
+            } else if ["foot", "mlterm", "contour"]
+                .iter()
+                .any(|name| term.starts_with(name))
+                || term.contains("sixel")
+                || program == "iTerm.app"
+            {
+                Graphics::Sixel
+            } else {
+                Graphics::HalfBlocks
+            }
+        }
+
+        pub fn as_str(self) -> &'static str {
+            match self {
+                Graphics::Kitty => "kitty",
+                Graphics::Sixel => "sixel",
+                Graphics::HalfBlocks => "halfblocks",
+                Graphics::Off => "off",
+            }
+        }
+    }
+
+    impl fmt::Display for Graphics {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
+
+    impl FromStr for Graphics {
+        type Err = String;
+
+        /// `auto` runs the detection.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_lowercase().as_str() {
+                "auto" => Ok(Graphics::detect()),
+                "kitty" => Ok(Graphics::Kitty),
+                "sixel" => Ok(Graphics::Sixel),
+                "halfblocks" => Ok(Graphics::HalfBlocks),
+                "off" => Ok(Graphics::Off),
+                other => Err(format!(
+                    "unknown thumbnail mode {:?}, expected auto, kitty, sixel, halfblocks or off",
+                    other
+                )),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    pub enum ThumbnailError {
+        Backend(BackendError),
+        Image(ImageError),
+    }
+
+    impl fmt::Display for ThumbnailError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ThumbnailError::Backend(e) => write!(f, "{}", e),
+                ThumbnailError::Image(e) => write!(f, "bad image: {}", e),
+            }
+        }
+    }
+
+    impl Error for ThumbnailError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                ThumbnailError::Backend(e) => Some(e),
+                ThumbnailError::Image(e) => Some(e),
+            }
+        }
+    }
+
+    impl From<BackendError> for ThumbnailError {
+        fn from(e: BackendError) -> Self {
+            ThumbnailError::Backend(e)
+        }
+    }
+
+    impl From<ImageError> for ThumbnailError {
+        fn from(e: ImageError) -> Self {
+            ThumbnailError::Image(e)
+        }
+    }
+
+    /// Decoded and scaled down thumbnails are kept in `$XDG_CACHE_HOME/yt-cli/thumbnails/`.
+    pub fn path(id: &str) -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| {
+            dir.join("yt-cli")
+                .join("thumbnails")
+                .join(format!("{}.png", id))
+        })
+    }
+
+    /// The thumbnail of `id`, from the disk cache or fetched from `url` and cached.
+    pub async fn fetch(
+        client: &PipedClient,
+        id: &str,
+        url: &str,
+    ) -> Result<RgbImage, ThumbnailError> {
+        let path = path(id);
+        if let Some(image) = path
+            .as_ref()
+            .and_then(|path| image::open(path).ok())
+            .map(|image| image.to_rgb8())
+        {
+            return Ok(image);
+        }
+
+        let body = client.thumbnail(url).await?;
+        let image = decode(&body)?;
+        if let Some(path) = path {
+            // the cache is only a shortcut, an image that cannot be saved is still shown
+            save(&path, &image).ok();
+        }
+        Ok(image)
+    }
+
+    /// Decodes a thumbnail, no wider than [`WIDTH`].
+    pub fn decode(body: &[u8]) -> Result<RgbImage, ImageError> {
+        let image = image::load_from_memory(body)?.to_rgb8();
+        if image.width() <= WIDTH {
+            return Ok(image);
+        }
+        let height = image.height() * WIDTH / image.width();
+        Ok(image::imageops::resize(
+            &image,
+            WIDTH,
+            height.max(1),
+            FilterType::Triangle,
+        ))
+    }
+
+    fn save(path: &PathBuf, image: &RgbImage) -> Result<(), ImageError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        image.save(path)
+    }
+
+    /// The largest size with the aspect ratio of `image` that fits into `width` x `height`.
+    pub fn fit(image: &RgbImage, width: u32, height: u32) -> (u32, u32) {
+        let (w, h) = image.dimensions();
+        if w == 0 || h == 0 {
+            return (0, 0);
+        }
+        if width * h <= height * w {
+            (width, (width * h / w).max(1))
+        } else {
+            ((height * w / h).max(1), height)
+        }
+    }
+
+    /// Scales `image` to exactly `width` x `height` pixels.
+    pub fn scale(image: &RgbImage, width: u32, height: u32) -> RgbImage {
+        image::imageops::resize(image, width.max(1), height.max(1), FilterType::Triangle)
+    }
+
+    /// Draws `image` at the cursor, stretched over `columns` x `rows` cells.
+    pub fn kitty(image: &RgbImage, columns: u16, rows: u16) -> io::Result<String> {
+        let mut png = Cursor::new(vec![]);
+        image
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let data = STANDARD.encode(png.into_inner());
+
+        let mut out = String::new();
+        let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = u8::from(i + 1 < chunks.len());
+            // base64 is ascii, every chunk is valid utf-8
+            let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+            if i == 0 {
+                write!(
+                    out,
+                    "\x1b_Ga=T,f=100,q=2,C=1,c={},r={},m={};{}\x1b\\",
+                    columns, rows, more, chunk
+                )
+                .ok();
+            } else {
+                write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk).ok();
+            }
+        }
+        Ok(out)
+    }
+
+    /// Draws `image` at the cursor as sixels, one pixel per dot.
+    pub fn sixel(image: &RgbImage) -> String {
+        let (width, height) = image.dimensions();
+        let level = |c: u8| c as u32 * (SIXEL_LEVELS - 1) / 255;
+        let colors: Vec<u32> = image
+            .pixels()
+            .map(|p| (level(p[0]) * SIXEL_LEVELS + level(p[1])) * SIXEL_LEVELS + level(p[2]))
+            .collect();
+
+        let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+        let percent = |l: u32| l * 100 / (SIXEL_LEVELS - 1);
+        for color in 0..SIXEL_LEVELS.pow(3) {
+            let (r, g, b) = (
+                color / (SIXEL_LEVELS * SIXEL_LEVELS),
+                color / SIXEL_LEVELS % SIXEL_LEVELS,
+                color % SIXEL_LEVELS,
+            );
+            write!(
+                out,
+                "#{};2;{};{};{}",
+                color,
+                percent(r),
+                percent(g),
+                percent(b)
+            )
+            .ok();
+        }
+
+        for top in (0..height).step_by(6) {
+            let rows = (height - top).min(6);
+            let mut used: Vec<u32> = (top..top + rows)
+                .flat_map(|y| {
+                    let start = (y * width) as usize;
+                    colors[start..start + width as usize].iter().copied()
+                })
+                .collect();
+            used.sort_unstable();
+            used.dedup();
+
+            for color in used {
+                write!(out, "#{}", color).ok();
+                let mut run = (0u8, 0u32);
+                for x in 0..width {
+                    let bits = (0..rows)
+                        .filter(|dy| colors[((top + dy) * width + x) as usize] == color)
+                        .fold(0u8, |bits, dy| bits | 1 << dy);
+                    let sixel = b'?' + bits;
+                    if run.1 > 0 && run.0 != sixel {
+                        push_run(&mut out, run);
+                        run.1 = 0;
+                    }
+                    run = (sixel, run.1 + 1);
+                }
+                push_run(&mut out, run);
+                // back to the start of the band for the next color
+                out.push('$');
+            }
+            out.push('-');
+        }
+        out.push_str("\x1b\\");
+        out
+    }
+
+    fn push_run(out: &mut String, (sixel, count): (u8, u32)) {
+        match count {
+            0 => {}
+            1..=3 => (0..count).for_each(|_| out.push(sixel as char)),
+            _ => {
+                write!(out, "!{}{}", count, sixel as char).ok();
+            }
+        }
+    }
+}
+
+pub mod subtitles {
+    use crate::backend::{html_to_text, Subtitle};
+    use std::{
+        fmt::Write,
+        path::{Path, PathBuf},
+    };
+
+    /// The track for the first language in `languages` that has one, uploaded captions
+    /// are picked over auto-generated ones. `en` matches `en-US` too.
+    pub fn pick<'a>(subtitles: &'a [Subtitle], languages: &[String]) -> Option<&'a Subtitle> {
+        languages.iter().find_map(|language| {
+            let language = language.to_ascii_lowercase();
+            let matching = subtitles.iter().filter(|s| {
+                let code = s.code.to_ascii_lowercase();
+                code == language || code.split('-').next() == Some(language.as_str())
+            });
+            matching
+                .clone()
+                .find(|s| !s.auto_generated)
+                .or_else(|| matching.clone().next())
+        })
+    }
+
+    /// `video.mp4` becomes `video.en.srt`.
+    pub fn srt_path(video_path: &Path, code: &str) -> PathBuf {
+        video_path.with_extension(format!("{}.srt", code))
+    }
+
+    /// Converts a WebVTT or TTML track to SRT, an SRT track is returned as it is.
+    pub fn to_srt(body: &str, mime_type: &str) -> String {
+        if mime_type.contains("ttml") || body.trim_start().starts_with("<?xml") {
+            ttml_to_srt(body)
+        } else if mime_type.contains("vtt") || body.trim_start().starts_with("WEBVTT") {
+            vtt_to_srt(body)
+        } else {
+            body.to_string()
+        }
+    }
+
+    fn vtt_to_srt(vtt: &str) -> String {
+        let mut cues = vec![];
+        let mut lines = vtt.lines().peekable();
+        while let Some(line) = lines.next() {
+            let Some((start, end)) = line.split_once("-->") else {
+                continue;
+            };
+            // cue settings like `align:start` follow the end time
+            let end = end.split_whitespace().next().unwrap_or_default();
+            let (Some(start), Some(end)) = (parse_time(start.trim()), parse_time(end)) else {
+                continue;
+            };
+            let mut text = vec![];
+            while let Some(line) = lines.next_if(|l| !l.trim().is_empty()) {
+                text.push(html_to_text(line));
+            }
+            cues.push((start, end, text.join("\n")));
+        }
+        format_srt(&cues)
+    }
+
+    fn ttml_to_srt(ttml: &str) -> String {
+        let mut cues = vec![];
+        let mut rest = ttml;
+        while let Some(i) = rest.find("<p") {
+            rest = &rest[i..];
+            let Some(tag_end) = rest.find('>') else {
+                break;
+            };
+            let tag = &rest[..tag_end];
+            if !(tag.starts_with("<p ") || tag == "<p") || tag.ends_with('/') {
+                rest = &rest[2..];
+                continue;
+            }
+            let Some(close) = rest.find("</p>") else {
+                break;
+            };
+            let body = &rest[tag_end + 1..close];
+            rest = &rest[close + 4..];
+
+            let Some(start) = attribute(tag, "begin").and_then(parse_time) else {
+                continue;
+            };
+            let end = match attribute(tag, "end").and_then(parse_time) {
+                Some(end) => end,
+                None => match attribute(tag, "dur").and_then(parse_time) {
+                    Some(dur) => start + dur,
+                    None => continue,
+                },
+            };
+            cues.push((start, end, html_to_text(&body.replace("<br />", "<br/>"))));
+        }
+        format_srt(&cues)
+    }
+
+    /// Cues with their start and end in milliseconds.
+    fn format_srt(cues: &[(u64, u64, String)]) -> String {
+        let mut srt = String::new();
+        for (i, (start, end, text)) in cues.iter().enumerate() {
+            writeln!(
+                srt,
+                "{}\n{} --> {}\n{}\n",
+                i + 1,
+                srt_time(*start),
+                srt_time(*end),
+                text.trim()
+            )
+            .unwrap();
+        }
+        srt
+    }
+
+    fn srt_time(ms: u64) -> String {
+        format!(
+            "{:02}:{:02}:{:02},{:03}",
+            ms / 3_600_000,
+            ms / 60_000 % 60,
+            ms / 1000 % 60,
+            ms % 1000
+        )
+    }
+
+    /// Milliseconds from `01:02:03.456`, `02:03.456`, `3.5s` or `3500ms`.
+    fn parse_time(time: &str) -> Option<u64> {
+        let seconds = |s: &str| s.parse::<f64>().ok().filter(|s| *s >= 0.0);
+        if let Some(ms) = time.strip_suffix("ms") {
+            return seconds(ms).map(|ms| ms as u64);
+        }
+        if let Some(s) = time.strip_suffix('s') {
+            return seconds(s).map(|s| (s * 1000.0).round() as u64);
+        }
+        let parts: Vec<&str> = time.split(':').collect();
+        let (h, m, s) = match parts.as_slice() {
+            [m, s] => ("0", *m, *s),
+            // a fourth part counts frames, they are dropped
+            [h, m, s] | [h, m, s, _] => (*h, *m, *s),
+            _ => return None,
+        };
+        let total = h.parse::<u64>().ok()? * 3600 + m.parse::<u64>().ok()? * 60;
+        Some(total * 1000 + (seconds(s)? * 1000.0).round() as u64)
+    }
+
+    fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+        let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+        let len = tag[start..].find('"')?;
+        Some(&tag[start..start + len])
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn vtt_drops_cue_settings_and_tags() {
+            let vtt = "WEBVTT\nKind: captions\nLanguage: en\n\n\
+                       00:00:01.000 --> 00:00:03.500 align:start position:0%\n\
+                       Hello <c.colorE5E5E5>there</c>\n\
+                       <i>general</i> &amp; co\n\n\
+                       01:02:03.004 --> 01:02:04.000\n\
+                       <00:00:01.200><c>Bye</c>\n";
+            assert_eq!(
+                to_srt(vtt, "text/vtt"),
+                "1\n00:00:01,000 --> 00:00:03,500\nHello there\ngeneral & co\n\n\
+                 2\n01:02:03,004 --> 01:02:04,000\nBye\n\n"
+            );
+        }
+
+        #[test]
+        fn ttml_uses_dur_without_end_and_keeps_line_breaks() {
+            let ttml = r#"<?xml version="1.0" encoding="utf-8" ?>
+<tt xml:lang="en"><body><div>
+<p begin="00:00:01.000" end="00:00:02.000">One<br />line</p>
+<p begin="2.5s" dur="1500ms">Two</p>
+<p begin="00:00:05:12" end="00:00:06:00">Frames</p>
+<p begin="bad" end="00:00:08.000">Skipped</p>
+</div></body></tt>"#;
+            assert_eq!(
+                to_srt(ttml, "application/ttml+xml"),
+                "1\n00:00:01,000 --> 00:00:02,000\nOne\nline\n\n\
+                 2\n00:00:02,500 --> 00:00:04,000\nTwo\n\n\
+                 3\n00:00:05,000 --> 00:00:06,000\nFrames\n\n"
+            );
+        }
+
+        #[test]
+        fn parses_every_time_format() {
+            assert_eq!(parse_time("01:02:03.456"), Some(3_723_456));
+            assert_eq!(parse_time("02:03.456"), Some(123_456));
+            assert_eq!(parse_time("00:00:05:12"), Some(5000));
+            assert_eq!(parse_time("3.5s"), Some(3500));
+            assert_eq!(parse_time("3500ms"), Some(3500));
+            assert_eq!(parse_time("-1s"), None);
+            assert_eq!(parse_time("later"), None);
+        }
+
+        fn track(code: &str, auto_generated: bool) -> Subtitle {
+            Subtitle {
+                url: format!("https://proxy.example/{}", code),
+                mime_type: "text/vtt".to_string(),
+                name: code.to_string(),
+                code: code.to_string(),
+                auto_generated,
+            }
+        }
+
+        #[test]
+        fn pick_prefers_uploaded_tracks_in_language_order() {
+            let tracks = [
+                track("en", true),
+                track("en-US", false),
+                track("de", true),
+                track("pt-BR", false),
+            ];
+            let pick = |languages: &[&str]| {
+                let languages: Vec<String> = languages.iter().map(|l| l.to_string()).collect();
+                pick(&tracks, &languages).map(|s| (s.code.as_str(), s.auto_generated))
+            };
+            assert_eq!(pick(&["en"]), Some(("en-US", false)));
+            assert_eq!(pick(&["EN-us"]), Some(("en-US", false)));
+            assert_eq!(pick(&["fr", "de"]), Some(("de", true)));
+            assert_eq!(pick(&["pt"]), Some(("pt-BR", false)));
+            assert_eq!(pick(&["fr"]), None);
+        }
     }
 }
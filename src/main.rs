@@ -1,49 +1,2146 @@
-use clap::{command, Arg};
+use clap::{command, Arg, ArgAction};
 use crossterm::{
+    cursor,
     event::{self, Event, KeyCode},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-    ExecutableCommand,
+    ExecutableCommand, QueueableCommand,
 };
+use image::RgbImage;
 use ratatui::{
-    layout::{Constraint, Direction, Layout},
-    prelude::{CrosstermBackend, Terminal},
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    prelude::{CrosstermBackend, Frame, Terminal},
     style::{Color, Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{
+        Block, BorderType, Borders, Clear, LineGauge, List, ListItem, ListState, Paragraph, Tabs,
+        Widget, Wrap,
+    },
 };
+use serde_json::Value;
 use std::{
-    io::{stdout, Result},
+    collections::{HashMap, VecDeque},
+    env, fs,
+    future::Future,
+    io::{stdout, Result, Write},
     ops::Not,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, Sender},
+    time::{Duration, Instant},
 };
-use yt_cli::backend;
+use tokio::{runtime::Runtime, task::JoinHandle};
+use yt_cli::{
+    backend::{
+        self, BackendError, Channel, Page, Playlist, QualityPreference, ResultKind, SearchFilter,
+        SearchSession, Segment, Stream, StreamSource, Subtitle, VideoDetails,
+    },
+    cache,
+    config::Config,
+    download::{self, Downloader},
+    ipc::{IpcError, MpvIpc, PlaybackStatus},
+    player::{self, PlayOptions, Player, PlayerProcess},
+    positions::{Position, Positions},
+    queue::{Queue, QueueItem},
+    subtitles,
+    thumbnails::{self, Graphics, ThumbnailError},
+};
+
+struct App {
+    active_block: usize,
+    search_input: String,
+    footer_text: String,
+    search_cursor_position: usize,
+    results: Vec<backend::OrangeResult>,
+    selected_item: usize,
+    video_state: ListState,
+    navigating_item: usize,
+    client: backend::PipedClient,
+    runtime: Runtime,
+    details: Option<backend::VideoDetails>,
+    details_scroll: u16,
+    channel: Option<ChannelView>,
+    playlist: Option<PlaylistView>,
+    filter: SearchFilter,
+    session: Option<SearchSession>,
+    suggestions: Vec<String>,
+    suggestion_state: ListState,
+    /// When the search input last changed, cleared once suggestions are requested.
+    search_edited_at: Option<Instant>,
+    suggestion_tx: Sender<(String, Vec<String>)>,
+    suggestion_rx: Receiver<(String, Vec<String>)>,
+    region: String,
+    region_picker: Option<ListState>,
+    player: Box<dyn Player>,
+    play_options: PlayOptions,
+    /// When set, videos play from the picked Piped streams instead of the watch url.
+    quality: Option<QualityPreference>,
+    quality_picker: Option<QualityPicker>,
+    stream_source: StreamSource,
+    /// The player process, only one runs at a time.
+    child: Option<PlayerProcess>,
+    queue: Queue,
+    /// Whether the next queued item starts once the player exits, off after a stop.
+    queue_running: bool,
+    queue_panel: Option<ListState>,
+    positions: Positions,
+    /// The id of the video the player is on, to remember its position.
+    now_playing: Option<String>,
+    resume_prompt: Option<ResumePrompt>,
+    /// Subtitle languages in order of preference.
+    subtitle_languages: Vec<String>,
+    /// The track picked for the next video, `Some(None)` plays it without subtitles.
+    subtitle_choice: Option<Option<Subtitle>>,
+    subtitle_picker: Option<SubtitlePicker>,
+    /// SponsorBlock categories to skip, none turns skipping off.
+    sponsor_categories: Vec<String>,
+    /// Segments to skip in the video that is playing.
+    segments: Vec<Segment>,
+    segment_tx: Sender<(String, Vec<Segment>)>,
+    segment_rx: Receiver<(String, Vec<Segment>)>,
+    toast: Option<(String, Instant)>,
+    /// The last search or trending load failed, shown over the results until retried.
+    results_error: Option<(String, Retry)>,
+    /// Whether the preview pane is shown next to the lists.
+    preview: bool,
+    /// Previews of the videos looked at last.
+    previews: Lru<VideoDetails>,
+    /// The video the running preview task fetches.
+    preview_id: Option<String>,
+    graphics: Graphics,
+    /// Terminal cell size in pixels, for sixels.
+    cell_size: (u32, u32),
+    /// Decoded thumbnails of the results looked at last.
+    thumbnails: Lru<RgbImage>,
+    /// The result the running thumbnail task fetches.
+    thumbnail_id: Option<String>,
+    /// Where the last frame left room for kitty or sixel graphics.
+    thumbnail_area: Option<Rect>,
+    /// The thumbnail on the terminal and where it is.
+    shown_thumbnail: Option<(String, Rect)>,
+    tasks: HashMap<Task, Running>,
+    task_id: u64,
+    loaded_tx: Sender<(Task, u64, Loaded)>,
+    loaded_rx: Receiver<(Task, u64, Loaded)>,
+    /// Drives the spinner.
+    started_at: Instant,
+    downloader: Downloader,
+    downloads: Vec<DownloadState>,
+    download_tx: Sender<DownloadEvent>,
+    download_rx: Receiver<DownloadEvent>,
+    ipc: Option<MpvIpc>,
+    playback: Option<PlaybackStatus>,
+    last_ipc_poll: Instant,
+}
+
+const IPC_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+const SEEK_STEP: f64 = 10.0;
+const VOLUME_STEP: f64 = 5.0;
+const SPEED_STEP: f64 = 1.1;
+
+/// How long typing has to pause before suggestions are fetched.
+const SUGGESTION_DEBOUNCE: Duration = Duration::from_millis(250);
+const MAX_SUGGESTIONS: usize = 8;
+
+/// How close to the end of the results the next page gets fetched.
+const PREFETCH_DISTANCE: usize = 5;
+
+struct PlaylistView {
+    playlist: backend::Playlist,
+    items: Vec<backend::OrangeResult>,
+    nextpage: Option<String>,
+    state: ListState,
+}
+
+impl PlaylistView {
+    fn new(mut playlist: backend::Playlist) -> Self {
+        let videos = std::mem::take(&mut playlist.videos);
+        let mut state = ListState::default();
+        if !videos.items.is_empty() {
+            state.select(Some(0));
+        }
+        Self {
+            playlist,
+            items: videos.items,
+            nextpage: videos.nextpage,
+            state,
+        }
+    }
+}
+
+/// Moves the selection one step, returns false when it is already at the end.
+fn list_move(state: &mut ListState, len: usize, up: bool) -> bool {
+    match state.selected() {
+        Some(i) if up && i > 0 => state.select(Some(i - 1)),
+        Some(i) if !up && i + 1 < len => state.select(Some(i + 1)),
+        None if len > 0 => state.select(Some(0)),
+        _ => return false,
+    }
+    true
+}
+
+struct ChannelTabView {
+    name: String,
+    /// `None` for the uploads, which come with the channel itself.
+    tab: Option<backend::ChannelTab>,
+    items: Vec<backend::OrangeResult>,
+    nextpage: Option<String>,
+    loaded: bool,
+}
+
+struct ChannelView {
+    channel: backend::Channel,
+    tabs: Vec<ChannelTabView>,
+    active_tab: usize,
+    state: ListState,
+}
+
+impl ChannelView {
+    fn new(mut channel: backend::Channel) -> Self {
+        let videos = std::mem::take(&mut channel.videos);
+        let mut tabs = vec![ChannelTabView {
+            name: "videos".to_string(),
+            tab: None,
+            items: videos.items,
+            nextpage: videos.nextpage,
+            loaded: true,
+        }];
+        tabs.extend(channel.tabs.iter().map(|tab| ChannelTabView {
+            name: tab.name.clone(),
+            tab: Some(tab.clone()),
+            items: vec![],
+            nextpage: None,
+            loaded: false,
+        }));
+        let mut state = ListState::default();
+        if !tabs[0].items.is_empty() {
+            state.select(Some(0));
+        }
+        Self {
+            channel,
+            tabs,
+            active_tab: 0,
+            state,
+        }
+    }
+
+    fn current(&self) -> &ChannelTabView {
+        &self.tabs[self.active_tab]
+    }
+}
+
+/// The streams of one video to choose from, the first entry follows the configured preference.
+struct QualityPicker {
+    id: String,
+    title: String,
+    video_streams: Vec<Stream>,
+    audio_streams: Vec<Stream>,
+    state: ListState,
+}
+
+impl QualityPicker {
+    fn new(details: &VideoDetails) -> Self {
+        let mut video_streams: Vec<Stream> = details
+            .video_streams
+            .iter()
+            .filter(|s| s.height > 0)
+            .cloned()
+            .collect();
+        video_streams.sort_by_key(|s| std::cmp::Reverse((s.height, s.fps, s.bitrate)));
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            id: details.id.clone(),
+            title: details.title.clone(),
+            video_streams,
+            audio_streams: details.audio_streams.clone(),
+            state,
+        }
+    }
+}
+
+/// Kinds of background work, a new task aborts the running one of its kind.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Task {
+    /// Search, trending and further result pages.
+    Results,
+    Details,
+    /// A playlist, its next page or all pages left.
+    Playlist,
+    Channel,
+    /// The streams and subtitles of the video about to play.
+    Play,
+    /// The streams of the video with this id, to download them.
+    Download(String),
+    /// The streams for the quality or subtitle picker.
+    Picker,
+    /// Metadata of the highlighted video for the preview pane.
+    Preview,
+    Thumbnail,
+}
+
+struct Running {
+    id: u64,
+    handle: JoinHandle<()>,
+}
+
+/// The request an error panel offers to repeat.
+enum Retry {
+    Search(String),
+    Trending(String),
+}
+
+/// What a background task brings back.
+enum Loaded {
+    Search(SearchSession, std::result::Result<Page, BackendError>),
+    MoreResults(SearchSession, std::result::Result<Page, BackendError>),
+    Trending(String, std::result::Result<Page, BackendError>),
+    Details(std::result::Result<VideoDetails, BackendError>),
+    Playlist(std::result::Result<Playlist, BackendError>),
+    Channel(std::result::Result<Channel, BackendError>),
+    Preview(String, std::result::Result<VideoDetails, BackendError>),
+    Thumbnail(String, std::result::Result<RgbImage, ThumbnailError>),
+    /// The streams of a video to play and the subtitle file saved for it.
+    Play {
+        id: String,
+        title: String,
+        start: Option<f64>,
+        details: std::result::Result<VideoDetails, BackendError>,
+        subtitles: std::result::Result<Option<PathBuf>, String>,
+    },
+    Download(std::result::Result<VideoDetails, BackendError>),
+    QualityPicker(std::result::Result<VideoDetails, BackendError>),
+    SubtitlePicker(std::result::Result<VideoDetails, BackendError>),
+    /// More videos of the playlist with this id, the whole playlist is played once they
+    /// are there when `play` is set.
+    PlaylistPage {
+        id: String,
+        page: std::result::Result<Page, BackendError>,
+        play: bool,
+    },
+    /// A page of the tab at this index of the channel with this id.
+    ChannelPage(String, usize, std::result::Result<Page, BackendError>),
+}
+
+/// How long the cursor has to rest on a video before its preview is fetched.
+const PREVIEW_DELAY: Duration = Duration::from_millis(250);
+
+/// How many decoded thumbnails stay in memory, older ones are read back from the disk
+/// cache when they are looked at again.
+const THUMBNAIL_CACHE: usize = 32;
+/// How many fetched previews stay in memory, older ones are fetched again.
+const PREVIEW_CACHE: usize = 64;
+
+/// Fetched values by result id, the error when fetching failed, dropping the least
+/// recently used ones past `capacity`.
+struct Lru<T> {
+    entries: HashMap<String, std::result::Result<T, String>>,
+    /// Ids from the least to the most recently used.
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl<T> Lru<T> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn get(&self, id: &str) -> Option<&std::result::Result<T, String>> {
+        self.entries.get(id)
+    }
+
+    /// Marks `id` as just used, returns whether it is cached.
+    fn touch(&mut self, id: &str) -> bool {
+        let Some(i) = self.order.iter().position(|cached| cached == id) else {
+            return false;
+        };
+        if let Some(id) = self.order.remove(i) {
+            self.order.push_back(id);
+        }
+        true
+    }
+
+    fn insert(&mut self, id: String, value: std::result::Result<T, String>) {
+        if self.entries.insert(id.clone(), value).is_some() {
+            self.touch(&id);
+            return;
+        }
+        self.order.push_back(id);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    /// Forgets the ids that failed to load.
+    fn retain_ok(&mut self) {
+        self.entries.retain(|_, value| value.is_ok());
+        let entries = &self.entries;
+        self.order.retain(|id| entries.contains_key(id));
+    }
+}
+
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_FRAME: Duration = Duration::from_millis(80);
+
+/// How far along a file download is, by its index in `App::downloads`.
+enum DownloadEvent {
+    Progress(usize, u64, Option<u64>),
+    Done(usize),
+    Failed(usize, String),
+}
+
+struct DownloadState {
+    path: PathBuf,
+    downloaded: u64,
+    total: Option<u64>,
+    running: bool,
+}
+
+/// Progress is reported at most once per this many bytes.
+const PROGRESS_STEP: u64 = 1 << 20;
+
+/// The caption tracks of one video, the first entry plays it without subtitles.
+struct SubtitlePicker {
+    id: String,
+    title: String,
+    subtitles: Vec<Subtitle>,
+    state: ListState,
+}
+
+/// A partly watched video waiting for the choice to resume it or start over.
+struct ResumePrompt {
+    id: String,
+    title: String,
+    position: Position,
+}
+
+/// The url and options that play `video` with the best audio track, or only the audio in
+/// audio-only mode.
+fn stream_playback(
+    title: &str,
+    video: Option<&Stream>,
+    audio_streams: &[Stream],
+    options: &PlayOptions,
+) -> Option<(String, PlayOptions)> {
+    let audio = backend::best_audio(audio_streams).map(|s| s.url.clone());
+    let mut options = options.clone();
+    options.title = Some(title.to_string());
+    options.direct = true;
+    let url = if options.audio_only {
+        audio?
+    } else {
+        let video = video?;
+        if video.video_only {
+            options.audio_file = audio;
+        }
+        video.url.clone()
+    };
+    Some((url, options))
+}
+
+/// Saves `subtitle` of the video `id` as SRT in the temp directory, for the player.
+async fn save_subtitle(
+    client: &backend::PipedClient,
+    id: &str,
+    subtitle: &Subtitle,
+) -> std::result::Result<PathBuf, String> {
+    let path = env::temp_dir().join(format!("yt-cli-{}.{}.srt", id, subtitle.code));
+    let srt = client.subtitle(subtitle).await.map_err(|e| e.to_string())?;
+    tokio::fs::write(&path, srt)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Plays the manifest when no quality is asked for, so the player adapts to the
+/// bandwidth, and the picked streams otherwise. A player without `separate_audio` gets
+/// a stream with the audio in it when there is one.
+fn piped_playback(
+    details: &VideoDetails,
+    quality: Option<&QualityPreference>,
+    options: &PlayOptions,
+    separate_audio: bool,
+) -> Option<(String, PlayOptions)> {
+    match (quality, details.manifest()) {
+        (None, Some(manifest)) if !options.audio_only => {
+            let mut options = options.clone();
+            options.title = Some(details.title.clone());
+            options.direct = true;
+            Some((manifest.to_string(), options))
+        }
+        (quality, _) => {
+            let quality = quality.cloned().unwrap_or_default();
+            let muxed: Vec<Stream> = details
+                .video_streams
+                .iter()
+                .filter(|s| !s.video_only)
+                .cloned()
+                .collect();
+            let video = quality
+                .pick(&muxed)
+                .filter(|_| !separate_audio)
+                .or_else(|| quality.pick(&details.video_streams));
+            stream_playback(&details.title, video, &details.audio_streams, options)
+        }
+    }
+}
+
+impl App {
+    fn new(
+        client: backend::PipedClient,
+        runtime: Runtime,
+        region: String,
+        player: Box<dyn Player>,
+        downloader: Downloader,
+    ) -> Self {
+        let (suggestion_tx, suggestion_rx) = mpsc::channel();
+        let (download_tx, download_rx) = mpsc::channel();
+        let (segment_tx, segment_rx) = mpsc::channel();
+        let (loaded_tx, loaded_rx) = mpsc::channel();
+        Self {
+            active_block: 1,
+            search_input: String::new(),
+            footer_text: String::new(),
+            search_cursor_position: 0,
+            results: vec![],
+            results_error: None,
+            selected_item: 0,
+            video_state: ListState::default(),
+            navigating_item: 0,
+            client,
+            runtime,
+            details: None,
+            details_scroll: 0,
+            channel: None,
+            playlist: None,
+            filter: SearchFilter::default(),
+            session: None,
+            suggestions: vec![],
+            suggestion_state: ListState::default(),
+            search_edited_at: None,
+            suggestion_tx,
+            suggestion_rx,
+            region,
+            region_picker: None,
+            player,
+            play_options: PlayOptions::default(),
+            quality: None,
+            quality_picker: None,
+            stream_source: StreamSource::default(),
+            child: None,
+            queue: Queue::default(),
+            queue_running: false,
+            queue_panel: None,
+            positions: Positions::default(),
+            now_playing: None,
+            resume_prompt: None,
+            subtitle_languages: vec![],
+            subtitle_choice: None,
+            subtitle_picker: None,
+            sponsor_categories: backend::SPONSOR_CATEGORIES
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+            segments: vec![],
+            segment_tx,
+            segment_rx,
+            toast: None,
+            preview: true,
+            previews: Lru::new(PREVIEW_CACHE),
+            preview_id: None,
+            graphics: Graphics::HalfBlocks,
+            cell_size: cell_size(),
+            thumbnails: Lru::new(THUMBNAIL_CACHE),
+            thumbnail_id: None,
+            thumbnail_area: None,
+            shown_thumbnail: None,
+            tasks: HashMap::new(),
+            task_id: 0,
+            loaded_tx,
+            loaded_rx,
+            started_at: Instant::now(),
+            downloader,
+            downloads: vec![],
+            download_tx,
+            download_rx,
+            ipc: None,
+            playback: None,
+            last_ipc_poll: Instant::now(),
+        }
+    }
+
+    /// Lists the cached trending page and fetches the current one.
+    fn start_trending(&mut self) {
+        match cache::load_trending(&self.region) {
+            Ok(results) => {
+                self.results = results;
+                self.video_state
+                    .select((!self.results.is_empty()).then_some(0));
+            }
+            Err(e) => self.footer_text = format!("Failed to load cached trending: {}", e),
+        }
+        let region = self.region.clone();
+        self.load_trending(&region);
+    }
+
+    /// Repeats the request behind the error panel.
+    fn retry(&mut self) {
+        let Some((_, retry)) = self.results_error.take() else {
+            return;
+        };
+        // previews and thumbnails that failed along with it get another chance too
+        self.previews.retain_ok();
+        self.thumbnails.retain_ok();
+        match retry {
+            Retry::Search(query) => self.search(&query),
+            Retry::Trending(region) => self.load_trending(&region),
+        }
+    }
+
+    /// Runs `future` in the background, its result comes back through [`App::update_tasks`].
+    ///
+    /// A task of the same kind that is still running is aborted, its result is no longer wanted.
+    fn spawn<F>(&mut self, task: Task, future: F)
+    where
+        F: Future<Output = Loaded> + Send + 'static,
+    {
+        self.task_id += 1;
+        let id = self.task_id;
+        let tx = self.loaded_tx.clone();
+        let sent = task.clone();
+        let handle = self.runtime.spawn(async move {
+            tx.send((sent, id, future.await)).ok();
+        });
+        if let Some(running) = self.tasks.insert(task, Running { id, handle }) {
+            running.handle.abort();
+        }
+    }
+
+    /// Whether anything but a preview is loading, previews show their own state.
+    fn is_loading(&self) -> bool {
+        self.tasks
+            .keys()
+            .any(|task| !matches!(task, Task::Preview | Task::Thumbnail))
+    }
+
+    /// Applies the results of finished background tasks.
+    fn update_tasks(&mut self) {
+        while let Ok((task, id, loaded)) = self.loaded_rx.try_recv() {
+            // a newer task of the same kind replaced this one
+            if self.tasks.get(&task).map(|running| running.id) != Some(id) {
+                continue;
+            }
+            self.tasks.remove(&task);
+            self.loaded(loaded);
+        }
+    }
+
+    fn loaded(&mut self, loaded: Loaded) {
+        match loaded {
+            Loaded::Search(session, Ok(page)) => {
+                self.results_error = None;
+                self.results = page.items;
+                self.navigating_item = 0;
+                self.video_state
+                    .select((!self.results.is_empty()).then_some(0));
+                self.footer_text = format!("Search results for: {}", session.query());
+                if page.skipped > 0 {
+                    self.footer_text += &format!(" ({} malformed skipped)", page.skipped);
+                }
+                self.session = Some(session);
+                self.details = None;
+                self.channel = None;
+                self.playlist = None;
+            }
+            Loaded::Search(session, Err(e)) => {
+                self.footer_text = format!("Search failed: {}", e);
+                self.results_error = Some((
+                    self.footer_text.clone(),
+                    Retry::Search(session.query().to_string()),
+                ));
+            }
+            Loaded::MoreResults(session, Ok(page)) => {
+                self.results.extend(page.items);
+                if self.video_state.selected().is_none() && !self.results.is_empty() {
+                    self.video_state.select(Some(0));
+                }
+                self.session = Some(session);
+            }
+            Loaded::MoreResults(_, Err(e)) => {
+                self.footer_text = format!("Failed to load more results: {}", e);
+            }
+            Loaded::Trending(region, Ok(page)) => {
+                self.results_error = None;
+                if let Err(e) = cache::save_trending(&region, &page.items) {
+                    self.footer_text = format!("Failed to cache trending: {}", e);
+                }
+                self.region = region;
+                self.results = page.items;
+                self.navigating_item = 0;
+                self.video_state
+                    .select((!self.results.is_empty()).then_some(0));
+                self.session = None;
+                self.details = None;
+                self.channel = None;
+                self.playlist = None;
+                self.footer_text = format!("Trending in {}", self.region);
+            }
+            Loaded::Trending(region, Err(e)) => {
+                self.footer_text = format!("Failed to load trending: {}", e);
+                self.results_error = Some((self.footer_text.clone(), Retry::Trending(region)));
+            }
+            Loaded::Thumbnail(id, image) => {
+                self.thumbnail_id = None;
+                self.thumbnails.insert(id, image.map_err(|e| e.to_string()));
+            }
+            Loaded::Preview(id, details) => {
+                self.preview_id = None;
+                self.previews.insert(id, details.map_err(|e| e.to_string()));
+            }
+            Loaded::Play {
+                id,
+                title,
+                start,
+                details,
+                subtitles,
+            } => self.play_loaded(id, &title, start, details, subtitles),
+            Loaded::Download(Ok(details)) => {
+                self.start_download(&details);
+                self.remember(details);
+            }
+            Loaded::Download(Err(e)) => {
+                self.footer_text = format!("Failed to load streams: {}", e);
+            }
+            Loaded::QualityPicker(Ok(details)) => {
+                self.quality_picker = Some(QualityPicker::new(&details));
+                self.remember(details);
+            }
+            Loaded::QualityPicker(Err(e)) => {
+                self.footer_text = format!("Failed to load streams: {}", e);
+            }
+            Loaded::SubtitlePicker(Ok(details)) => {
+                if details.subtitles.is_empty() {
+                    self.footer_text = format!("No subtitles for {}", details.title);
+                } else {
+                    let mut state = ListState::default();
+                    state.select(Some(0));
+                    self.subtitle_picker = Some(SubtitlePicker {
+                        id: details.id.clone(),
+                        title: details.title.clone(),
+                        subtitles: details.subtitles.clone(),
+                        state,
+                    });
+                }
+                self.remember(details);
+            }
+            Loaded::SubtitlePicker(Err(e)) => {
+                self.footer_text = format!("Failed to load subtitles: {}", e);
+            }
+            Loaded::PlaylistPage { id, page, play } => {
+                let Some(view) = self.playlist.as_mut().filter(|view| view.playlist.id == id)
+                else {
+                    return;
+                };
+                match page {
+                    Ok(page) => {
+                        view.items.extend(page.items);
+                        view.nextpage = page.nextpage;
+                        if view.state.selected().is_none() && !view.items.is_empty() {
+                            view.state.select(Some(0));
+                        }
+                        if play {
+                            self.start_playlist();
+                        }
+                    }
+                    Err(e) => self.footer_text = format!("Failed to load playlist: {}", e),
+                }
+            }
+            Loaded::Details(Ok(details)) => {
+                self.details = Some(details);
+                self.details_scroll = 0;
+            }
+            Loaded::Details(Err(e)) => self.footer_text = format!("Failed to load details: {}", e),
+            Loaded::Playlist(Ok(playlist)) => {
+                self.footer_text = format!(
+                    "Playlist: {} ({} videos) | Press p to play all",
+                    playlist.name, playlist.video_count
+                );
+                self.playlist = Some(PlaylistView::new(playlist));
+            }
+            Loaded::Playlist(Err(e)) => {
+                self.footer_text = format!("Failed to load playlist: {}", e);
+            }
+            Loaded::Channel(Ok(channel)) => {
+                self.footer_text = format!("Channel: {}", channel.name);
+                self.channel = Some(ChannelView::new(channel));
+            }
+            Loaded::Channel(Err(e)) => self.footer_text = format!("Failed to load channel: {}", e),
+            Loaded::ChannelPage(id, index, page) => {
+                let Some(view) = self.channel.as_mut().filter(|view| view.channel.id == id) else {
+                    return;
+                };
+                let tab = &mut view.tabs[index];
+                match page {
+                    Ok(page) => {
+                        tab.items.extend(page.items);
+                        tab.nextpage = page.nextpage;
+                        tab.loaded = true;
+                        if index == view.active_tab
+                            && view.state.selected().is_none()
+                            && !tab.items.is_empty()
+                        {
+                            view.state.select(Some(0));
+                        }
+                    }
+                    Err(e) => self.footer_text = format!("Failed to load {}: {}", tab.name, e),
+                }
+            }
+        }
+    }
+
+    /// Starts a search, replacing one that is still loading.
+    fn search(&mut self, query: &str) {
+        let mut session = self.client.search(query, self.filter);
+        self.footer_text = format!("Searching: {}", query);
+        self.spawn(Task::Results, async move {
+            let page = session.next_page().await;
+            Loaded::Search(session, page)
+        });
+    }
+
+    /// Switches to the trending page of `region`, dropping the current search.
+    fn load_trending(&mut self, region: &str) {
+        let client = self.client.clone();
+        let region = region.to_string();
+        self.spawn(Task::Results, async move {
+            let page = client.trending(&region).await;
+            Loaded::Trending(region, page)
+        });
+    }
+
+    /// Fetches the preview of the highlighted video unless it is known or on its way.
+    fn update_preview(&mut self) {
+        if !self.preview || self.details.is_some() {
+            return;
+        }
+        let Some(result) = self.highlighted() else {
+            return;
+        };
+        let id = result.id.clone();
+        let is_video = result.kind.is_video();
+        let thumbnail = result
+            .thumbnail
+            .clone()
+            .filter(|_| self.graphics != Graphics::Off);
+
+        if is_video
+            && !self.previews.touch(&id)
+            && !(self.preview_id.as_ref() == Some(&id) && self.tasks.contains_key(&Task::Preview))
+        {
+            let client = self.client.clone();
+            let id = id.clone();
+            self.preview_id = Some(id.clone());
+            self.spawn(Task::Preview, async move {
+                // moving on within the delay aborts the task before it asks the instance
+                tokio::time::sleep(PREVIEW_DELAY).await;
+                let details = client.video_details(&id).await;
+                Loaded::Preview(id, details)
+            });
+        }
+
+        if let Some(url) = thumbnail {
+            if self.thumbnails.touch(&id)
+                || (self.thumbnail_id.as_ref() == Some(&id)
+                    && self.tasks.contains_key(&Task::Thumbnail))
+            {
+                return;
+            }
+            let client = self.client.clone();
+            self.thumbnail_id = Some(id.clone());
+            self.spawn(Task::Thumbnail, async move {
+                tokio::time::sleep(PREVIEW_DELAY).await;
+                let image = thumbnails::fetch(&client, &id, &url).await;
+                Loaded::Thumbnail(id, image)
+            });
+        }
+    }
+
+    /// Whether a popup covers part of the screen, terminal graphics would draw over it.
+    fn has_overlay(&self) -> bool {
+        self.region_picker.is_some()
+            || self.quality_picker.is_some()
+            || self.subtitle_picker.is_some()
+            || self.queue_panel.is_some()
+            || self.resume_prompt.is_some()
+            || (self.results_error.is_some() && !self.is_loading())
+            || (self.active_block == 0 && !self.suggestions.is_empty())
+    }
+
+    /// Puts the thumbnail the last frame made room for on the terminal, when it changed.
+    fn show_thumbnail(&mut self) -> Result<()> {
+        let wanted = self
+            .thumbnail_area
+            .and_then(|area| Some((self.highlighted()?.id.clone(), area)));
+        if wanted == self.shown_thumbnail {
+            return Ok(());
+        }
+        let mut out = stdout();
+        if self.graphics == Graphics::Kitty && self.shown_thumbnail.is_some() {
+            out.write_all(thumbnails::KITTY_CLEAR.as_bytes())?;
+        }
+        if let Some((id, area)) = &wanted {
+            if let Some(Ok(image)) = self.thumbnails.get(id) {
+                out.queue(cursor::MoveTo(area.x, area.y))?;
+                match self.graphics {
+                    Graphics::Kitty => out
+                        .write_all(thumbnails::kitty(image, area.width, area.height)?.as_bytes())?,
+                    Graphics::Sixel => {
+                        let (width, height) = thumbnails::fit(
+                            image,
+                            area.width as u32 * self.cell_size.0,
+                            area.height as u32 * self.cell_size.1,
+                        );
+                        let image = thumbnails::scale(image, width, height);
+                        out.write_all(thumbnails::sixel(&image).as_bytes())?;
+                    }
+                    Graphics::HalfBlocks | Graphics::Off => {}
+                }
+            }
+        }
+        out.flush()?;
+        self.shown_thumbnail = wanted;
+        Ok(())
+    }
+
+    fn open_details(&mut self, id: &str) {
+        let client = self.client.clone();
+        let id = id.to_string();
+        self.spawn(Task::Details, async move {
+            Loaded::Details(client.video_details(&id).await)
+        });
+    }
+
+    /// Connects to the player's IPC socket once it shows up and refreshes the playback status.
+    fn poll_playback(&mut self) {
+        if self.last_ipc_poll.elapsed() < IPC_POLL_INTERVAL {
+            return;
+        }
+        self.last_ipc_poll = Instant::now();
+
+        if self.ipc.is_none() {
+            if let Some(path) = self.player.ipc_socket().filter(|path| path.exists()) {
+                self.ipc = MpvIpc::connect(path).ok();
+            }
+        }
+        if let Some(ipc) = &mut self.ipc {
+            match ipc.status() {
+                Ok(status) => {
+                    if let Some(id) = &self.now_playing {
+                        self.positions.update(id, status.position, status.duration);
+                    }
+                    // the end is left out so a skip never lands back in the segment
+                    let skip = self.segments.iter().find(|s| {
+                        status.position >= s.segment.0 && status.position < s.segment.1 - 0.5
+                    });
+                    if let Some(segment) = skip {
+                        if ipc
+                            .set_property("time-pos", Value::from(segment.segment.1))
+                            .is_ok()
+                        {
+                            self.toast =
+                                Some((format!("Skipped {}", segment.category), Instant::now()));
+                        }
+                    }
+                    self.playback = Some(status);
+                }
+                // the player went away
+                Err(_) => {
+                    self.ipc = None;
+                    self.playback = None;
+                }
+            }
+        }
+    }
+
+    /// Sends a command to the running player and refreshes the status right after.
+    fn control(&mut self, command: impl FnOnce(&mut MpvIpc) -> std::result::Result<(), IpcError>) {
+        let Some(ipc) = &mut self.ipc else {
+            return;
+        };
+        if let Err(e) = command(ipc) {
+            self.footer_text = e.to_string();
+        }
+        self.last_ipc_poll = Instant::now() - IPC_POLL_INTERVAL;
+    }
+
+    fn search_edited(&mut self) {
+        self.search_edited_at = Some(Instant::now());
+        self.suggestion_state.select(None);
+    }
+
+    /// Requests suggestions once typing has paused, and picks up the ones that arrived.
+    fn update_suggestions(&mut self) {
+        if self
+            .search_edited_at
+            .is_some_and(|t| t.elapsed() >= SUGGESTION_DEBOUNCE)
+        {
+            self.search_edited_at = None;
+            let query = self.search_input.clone();
+            let client = self.client.clone();
+            let tx = self.suggestion_tx.clone();
+            self.runtime.spawn(async move {
+                let suggestions = client.suggestions(&query).await.unwrap_or_default();
+                tx.send((query, suggestions)).ok();
+            });
+        }
+
+        while let Ok((query, mut suggestions)) = self.suggestion_rx.try_recv() {
+            // answers for anything but the current input are stale
+            if query == self.search_input && self.active_block == 0 {
+                suggestions.truncate(MAX_SUGGESTIONS);
+                self.suggestions = suggestions;
+                self.suggestion_state.select(None);
+            }
+        }
+    }
+
+    /// Starts downloading the video in the detail view or the highlighted one.
+    fn download(&mut self) {
+        let id = match &self.details {
+            Some(details) => details.id.clone(),
+            None => match self.highlighted().filter(|r| r.kind.is_video()) {
+                Some(r) => r.id.clone(),
+                None => return,
+            },
+        };
+        let details = self.video_details(&id);
+        self.spawn(Task::Download(id), async move {
+            Loaded::Download(details.await)
+        });
+    }
+
+    /// Downloads the streams picked from `details`.
+    fn start_download(&mut self, details: &VideoDetails) {
+        let quality = self.quality.clone().unwrap_or_default();
+        let jobs = self
+            .downloader
+            .jobs(details, &quality, self.play_options.audio_only);
+        if jobs.is_empty() {
+            self.footer_text = format!("No downloadable stream for {}", details.title);
+            return;
+        }
+        if jobs.iter().any(|job| {
+            self.downloads
+                .iter()
+                .any(|state| state.running && state.path == job.path)
+        }) {
+            self.footer_text = format!("Already downloading: {}", details.title);
+            return;
+        }
+
+        for job in jobs {
+            let i = self.downloads.len();
+            self.downloads.push(DownloadState {
+                path: job.path.clone(),
+                downloaded: 0,
+                total: None,
+                running: true,
+            });
+            let downloader = self.downloader.clone();
+            let tx = self.download_tx.clone();
+            self.runtime.spawn(async move {
+                let progress_tx = tx.clone();
+                let mut reported = 0;
+                let result = downloader
+                    .download(&job.url, &job.path, |downloaded, total| {
+                        if downloaded - reported >= PROGRESS_STEP || Some(downloaded) == total {
+                            reported = downloaded;
+                            progress_tx
+                                .send(DownloadEvent::Progress(i, downloaded, total))
+                                .ok();
+                        }
+                    })
+                    .await;
+                tx.send(match result {
+                    Ok(()) => DownloadEvent::Done(i),
+                    Err(e) => DownloadEvent::Failed(i, e.to_string()),
+                })
+                .ok();
+            });
+        }
+        self.footer_text = format!(
+            "Downloading: {} to {}",
+            details.title,
+            self.downloader.dir().display()
+        );
+    }
+
+    fn update_downloads(&mut self) {
+        while let Ok(event) = self.download_rx.try_recv() {
+            match event {
+                DownloadEvent::Progress(i, downloaded, total) => {
+                    self.downloads[i].downloaded = downloaded;
+                    self.downloads[i].total = total;
+                }
+                DownloadEvent::Done(i) => {
+                    self.downloads[i].running = false;
+                    self.footer_text = format!("Downloaded: {}", self.downloads[i].path.display());
+                }
+                DownloadEvent::Failed(i, e) => {
+                    self.downloads[i].running = false;
+                    self.footer_text = format!(
+                        "Failed to download {}: {}",
+                        self.downloads[i].path.display(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    fn clear_suggestions(&mut self) {
+        self.suggestions.clear();
+        self.suggestion_state.select(None);
+        self.search_edited_at = None;
+    }
+
+    /// Appends the next page of search results once the cursor gets close to the end.
+    fn load_more_results(&mut self) {
+        if self.navigating_item + PREFETCH_DISTANCE < self.results.len() {
+            return;
+        }
+        // a page or a new search is on its way already
+        if self.tasks.contains_key(&Task::Results) {
+            return;
+        }
+        let Some(mut session) = self.session.clone().filter(|s| !s.is_exhausted()) else {
+            return;
+        };
+        self.spawn(Task::Results, async move {
+            let page = session.next_page().await;
+            Loaded::MoreResults(session, page)
+        });
+    }
+
+    /// The item under the cursor in whichever list is showing.
+    fn highlighted(&self) -> Option<&backend::OrangeResult> {
+        if let Some(view) = &self.playlist {
+            return view.state.selected().and_then(|i| view.items.get(i));
+        }
+        match &self.channel {
+            Some(channel) => channel
+                .state
+                .selected()
+                .and_then(|i| channel.current().items.get(i)),
+            None => self.results.get(self.navigating_item),
+        }
+    }
+
+    fn open_playlist(&mut self, id: &str) {
+        let client = self.client.clone();
+        let id = id.to_string();
+        self.spawn(Task::Playlist, async move {
+            Loaded::Playlist(client.playlist(&id).await)
+        });
+    }
+
+    /// Fetches the next page of the open playlist.
+    fn load_playlist_page(&mut self) {
+        // a page or the whole rest of the playlist is on its way already
+        if self.tasks.contains_key(&Task::Playlist) {
+            return;
+        }
+        let Some(view) = &self.playlist else {
+            return;
+        };
+        let Some(nextpage) = view.nextpage.clone() else {
+            return;
+        };
+        let client = self.client.clone();
+        let id = view.playlist.id.clone();
+        self.spawn(Task::Playlist, async move {
+            let page = client.playlist_next_page(&id, &nextpage).await;
+            Loaded::PlaylistPage {
+                id,
+                page,
+                play: false,
+            }
+        });
+    }
+
+    /// Starts the player, replacing whatever it plays now so there is only ever one window.
+    fn start(&mut self, urls: &[String], options: &PlayOptions) -> bool {
+        self.stop_player();
+        match self.player.spawn(urls, options) {
+            Ok(child) => {
+                self.child = Some(child);
+                true
+            }
+            Err(e) => {
+                self.play_failed(format!("Failed to play: {}", e));
+                false
+            }
+        }
+    }
+
+    /// Reports a video that could not be started, stopping the queue so one bad entry
+    /// does not run through all the ones after it.
+    fn play_failed(&mut self, message: String) {
+        self.footer_text = message;
+        if self.queue_running {
+            self.queue_running = false;
+            self.footer_text += &format!(" | queue stopped, {} left", self.queue.len());
+        }
+    }
+
+    /// Stops the player, asking mpv to quit before killing it.
+    fn stop_player(&mut self) {
+        let Some(process) = self.child.take() else {
+            return;
+        };
+        if self.ipc.take().is_some_and(|mut ipc| ipc.stop().is_ok()) {
+            process.wait().ok();
+        } else {
+            process.kill();
+        }
+        self.playback = None;
+        self.finish_playing();
+    }
+
+    /// Remembers which video plays and looks up its SponsorBlock segments.
+    fn playing(&mut self, id: &str) {
+        self.now_playing = Some(id.to_string());
+        self.segments.clear();
+        if self.sponsor_categories.is_empty() {
+            return;
+        }
+        let id = id.to_string();
+        let categories = self.sponsor_categories.clone();
+        let client = self.client.clone();
+        let tx = self.segment_tx.clone();
+        self.runtime.spawn(async move {
+            let segments = client.sponsors(&id, &categories).await.unwrap_or_default();
+            tx.send((id, segments)).ok();
+        });
+    }
+
+    fn update_segments(&mut self) {
+        while let Ok((id, segments)) = self.segment_rx.try_recv() {
+            if self.now_playing.as_deref() == Some(id.as_str()) {
+                self.segments = segments;
+            }
+        }
+        if self
+            .toast
+            .as_ref()
+            .is_some_and(|(_, at)| at.elapsed() >= TOAST_DURATION)
+        {
+            self.toast = None;
+        }
+    }
+
+    /// Forgets the current video and saves how far it got.
+    fn finish_playing(&mut self) {
+        self.now_playing = None;
+        self.segments.clear();
+        if let Err(e) = self.positions.save() {
+            self.footer_text = format!("Failed to save positions: {}", e);
+        }
+    }
+
+    /// Notices when the player exits and starts the next queued item.
+    fn poll_player(&mut self) {
+        if let Some(process) = &mut self.child {
+            match process.try_wait() {
+                Ok(None) => return,
+                Ok(Some(outcome)) => self.footer_text = outcome.to_string(),
+                Err(e) => self.footer_text = format!("Lost track of the player: {}", e),
+            }
+            self.child = None;
+            self.ipc = None;
+            self.playback = None;
+            self.finish_playing();
+        }
+        // the queued video asking whether to resume, or loading its streams, has not
+        // started yet
+        if !self.queue_running
+            || self.resume_prompt.is_some()
+            || self.tasks.contains_key(&Task::Play)
+        {
+            return;
+        }
+        match self.queue.pop() {
+            Some(item) => {
+                if let Some(state) = &mut self.queue_panel {
+                    state.select((!self.queue.is_empty()).then_some(0));
+                }
+                self.play_video(&item.id, &item.title);
+            }
+            None => self.queue_running = false,
+        }
+    }
+
+    /// Plays a video, asking first whether to resume it when it was left partly watched.
+    fn play_video(&mut self, id: &str, title: &str) {
+        match self.positions.get(id) {
+            Some(position) => {
+                self.resume_prompt = Some(ResumePrompt {
+                    id: id.to_string(),
+                    title: title.to_string(),
+                    position,
+                });
+            }
+            None => self.play_video_at(id, title, None),
+        }
+    }
+
+    /// Plays a video from `start` seconds, from the Piped streams when they are the source
+    /// or a quality is set.
+    fn play_video_at(&mut self, id: &str, title: &str, start: Option<f64>) {
+        let from_youtube = self.quality.is_none() && self.stream_source == StreamSource::Youtube;
+        let wants_subtitles = !self.subtitle_languages.is_empty() || self.subtitle_choice.is_some();
+        if from_youtube && !wants_subtitles {
+            let mut options = self.play_options.clone();
+            options.start = start;
+            if self.start(&[backend::watch_url(id)], &options) {
+                self.playing(id);
+                self.footer_text = format!("Playing: {}", title);
+            }
+            return;
+        }
+
+        let details = self.video_details(id);
+        let choice = self.subtitle_choice.take();
+        let languages = self.subtitle_languages.clone();
+        let client = self.client.clone();
+        let (id, title) = (id.to_string(), title.to_string());
+        self.footer_text = format!("Loading: {}", title);
+        self.spawn(Task::Play, async move {
+            let details = details.await;
+            // the picked track, or the one in the preferred languages
+            let subtitle = match (choice, &details) {
+                (Some(choice), _) => choice,
+                (None, Ok(details)) => subtitles::pick(&details.subtitles, &languages).cloned(),
+                (None, Err(_)) => None,
+            };
+            let subtitles = match subtitle {
+                Some(subtitle) => save_subtitle(&client, &id, &subtitle).await.map(Some),
+                None => Ok(None),
+            };
+            Loaded::Play {
+                id,
+                title,
+                start,
+                details,
+                subtitles,
+            }
+        });
+    }
+
+    /// Starts a video whose streams were fetched by [`App::play_video_at`].
+    fn play_loaded(
+        &mut self,
+        id: String,
+        title: &str,
+        start: Option<f64>,
+        details: std::result::Result<VideoDetails, BackendError>,
+        subtitles: std::result::Result<Option<PathBuf>, String>,
+    ) {
+        let details = match details {
+            Ok(details) => details,
+            Err(e) => {
+                self.play_failed(format!("Failed to load streams: {}", e));
+                return;
+            }
+        };
+        let mut options = self.play_options.clone();
+        options.start = start;
+        options.sub_files = subtitles.clone().ok().flatten().into_iter().collect();
+        let from_youtube = self.quality.is_none() && self.stream_source == StreamSource::Youtube;
+        let playback = if from_youtube {
+            Some((backend::watch_url(&id), options))
+        } else {
+            piped_playback(
+                &details,
+                self.quality.as_ref(),
+                &options,
+                self.player.separate_audio(),
+            )
+        };
+        match playback {
+            Some((url, options)) => {
+                if self.start(&[url], &options) {
+                    self.playing(&id);
+                    self.footer_text = format!("Playing: {}", details.title);
+                    if let Err(e) = subtitles {
+                        self.footer_text += &format!(" | failed to load subtitles: {}", e);
+                    }
+                }
+            }
+            None => self.play_failed(format!("No playable stream for {}", title)),
+        }
+        self.remember(details);
+    }
+
+    /// The streams of `id`, from the preview cache when the preview pane has them already.
+    fn video_details(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = std::result::Result<VideoDetails, BackendError>> + Send + 'static
+    {
+        let cached = self
+            .previews
+            .get(id)
+            .and_then(|preview| preview.as_ref().ok())
+            .cloned();
+        let client = self.client.clone();
+        let id = id.to_string();
+        async move {
+            match cached {
+                Some(details) => Ok(details),
+                None => client.video_details(&id).await,
+            }
+        }
+    }
+
+    /// Keeps fetched streams for the preview pane and the next action on the same video.
+    fn remember(&mut self, details: VideoDetails) {
+        self.previews.insert(details.id.clone(), Ok(details));
+    }
+
+    /// Opens the subtitle picker for the video in the detail view or the highlighted one.
+    fn open_subtitle_picker(&mut self) {
+        let id = match &self.details {
+            Some(details) => details.id.clone(),
+            None => match self.highlighted().filter(|r| r.kind.is_video()) {
+                Some(r) => r.id.clone(),
+                None => return,
+            },
+        };
+        let details = self.video_details(&id);
+        self.spawn(Task::Picker, async move {
+            Loaded::SubtitlePicker(details.await)
+        });
+    }
+
+    fn play_streams(
+        &mut self,
+        id: &str,
+        title: &str,
+        video: Option<&Stream>,
+        audio_streams: &[Stream],
+    ) {
+        match stream_playback(title, video, audio_streams, &self.play_options) {
+            Some((url, options)) => {
+                if !self.start(&[url], &options) {
+                    return;
+                }
+                self.playing(id);
+                self.footer_text = format!(
+                    "Playing: {} ({})",
+                    title,
+                    match video {
+                        Some(video) if !options.audio_only => video.quality.as_str(),
+                        _ => "audio",
+                    }
+                );
+                if options.audio_file.is_some() && !self.player.separate_audio() {
+                    self.footer_text += &format!(
+                        " | {} plays no separate audio track, add {{audio}} to its template",
+                        self.player.name()
+                    );
+                }
+            }
+            None => self.play_failed(format!("No playable stream for {}", title)),
+        }
+    }
+
+    /// Opens the quality picker for the video in the detail view or the highlighted one.
+    fn open_quality_picker(&mut self) {
+        if let Some(details) = &self.details {
+            self.quality_picker = Some(QualityPicker::new(details));
+            return;
+        }
+        let Some(id) = self
+            .highlighted()
+            .filter(|r| r.kind.is_video())
+            .map(|r| r.id.clone())
+        else {
+            return;
+        };
+        let details = self.video_details(&id);
+        self.spawn(
+            Task::Picker,
+            async move { Loaded::QualityPicker(details.await) },
+        );
+    }
+
+    /// Loads the rest of the open playlist and hands all of it to the player.
+    fn play_playlist(&mut self) {
+        let Some(view) = &self.playlist else {
+            return;
+        };
+        let Some(nextpage) = view.nextpage.clone() else {
+            self.start_playlist();
+            return;
+        };
+        let client = self.client.clone();
+        let id = view.playlist.id.clone();
+        self.footer_text = format!("Loading the rest of {}", view.playlist.name);
+        // replaces a running page load, the rest starts from the same page
+        self.spawn(Task::Playlist, async move {
+            let mut rest = Page::default();
+            let mut nextpage = Some(nextpage);
+            while let Some(token) = nextpage.take() {
+                match client.playlist_next_page(&id, &token).await {
+                    Ok(page) => {
+                        rest.items.extend(page.items);
+                        rest.skipped += page.skipped;
+                        nextpage = page.nextpage;
+                    }
+                    Err(e) => {
+                        return Loaded::PlaylistPage {
+                            id,
+                            page: Err(e),
+                            play: true,
+                        }
+                    }
+                }
+            }
+            Loaded::PlaylistPage {
+                id,
+                page: Ok(rest),
+                play: true,
+            }
+        });
+    }
+
+    /// Hands the loaded playlist to the player, or plays it through the queue when the
+    /// videos need their Piped streams.
+    fn start_playlist(&mut self) {
+        let Some(view) = &self.playlist else {
+            return;
+        };
+        let name = view.playlist.name.clone();
+        if self.quality.is_none() && self.stream_source == StreamSource::Youtube {
+            let urls: Vec<String> = view.items.iter().map(|r| r.url.clone()).collect();
+            if self.start(&urls, &self.play_options.clone()) {
+                self.footer_text = format!("Playing playlist: {} ({} videos)", name, urls.len());
+            }
+            return;
+        }
+
+        let mut items = view
+            .items
+            .iter()
+            .filter(|r| r.kind.is_video())
+            .map(QueueItem::from);
+        let Some(first) = items.next() else {
+            return;
+        };
+        let rest: Vec<QueueItem> = items.collect();
+        self.footer_text = format!("Playing playlist: {} ({} queued)", name, rest.len());
+        // the rest goes ahead of whatever was queued before
+        self.queue.push_front(rest);
+        self.queue_running = true;
+        self.play_video(&first.id, &first.title);
+    }
+
+    /// Adds the highlighted video to the queue, it starts right away when nothing plays.
+    fn enqueue(&mut self) {
+        let Some(item) = self
+            .highlighted()
+            .filter(|r| r.kind.is_video())
+            .map(QueueItem::from)
+        else {
+            return;
+        };
+        self.footer_text = format!("Queued: {} ({} in queue)", item.title, self.queue.len() + 1);
+        self.queue.push(item);
+        // plays right away when nothing plays, after the current video otherwise
+        self.queue_running = true;
+    }
+
+    fn open_channel(&mut self) {
+        let Some(id) = self.highlighted().and_then(|r| r.channel_id.clone()) else {
+            self.footer_text = "No channel for this item".to_string();
+            return;
+        };
+        let client = self.client.clone();
+        self.spawn(Task::Channel, async move {
+            Loaded::Channel(client.channel(&id).await)
+        });
+    }
+
+    /// Loads the active channel tab if it was never opened, or its next page.
+    fn load_channel_page(&mut self) {
+        let Some(view) = &mut self.channel else {
+            return;
+        };
+        let id = view.channel.id.clone();
+        let index = view.active_tab;
+        let tab = &view.tabs[index];
+        if tab.loaded && tab.nextpage.is_none() {
+            return;
+        }
+        // the next page of this tab is on its way already
+        if tab.loaded && self.tasks.contains_key(&Task::Channel) {
+            return;
+        }
+
+        let client = self.client.clone();
+        let (channel_tab, nextpage) = (tab.tab.clone(), tab.nextpage.clone());
+        if channel_tab.is_none() && nextpage.is_none() {
+            return;
+        }
+        self.spawn(Task::Channel, async move {
+            let page = match (&channel_tab, &nextpage) {
+                (Some(channel_tab), nextpage) => {
+                    client.channel_tab(channel_tab, nextpage.as_deref()).await
+                }
+                (None, nextpage) => {
+                    client
+                        .channel_next_page(&id, nextpage.as_deref().unwrap_or_default())
+                        .await
+                }
+            };
+            Loaded::ChannelPage(id, index, page)
+        });
+    }
+}
+
+fn result_item<'a>(i: usize, r: &'a backend::OrangeResult, positions: &Positions) -> ListItem<'a> {
+    let index = Span::styled(
+        format!("{}. ", i + 1),
+        Style::default().fg(Color::Rgb(198, 160, 246)),
+    );
+    let title = Span::styled(
+        r.title.to_string(),
+        Style::default().fg(Color::White).bold(),
+    );
+    let verified = Span::styled(
+        " [ ".to_string(),
+        Style::default()
+            .fg(if r.is_verified {
+                Color::Rgb(166, 218, 149)
+            } else {
+                Color::Rgb(237, 135, 150)
+            })
+            .bold(),
+    );
+    let uploader = Span::styled(
+        format!("{}]", r.uploader),
+        Style::default().fg(Color::Rgb(245, 169, 127)).bold(),
+    );
+    let duration = match r.kind {
+        ResultKind::Video => format!(" [󰔛 {}]", r.duration),
+        ResultKind::Short => format!(" [󰔛 {} short]", r.duration),
+        ResultKind::Livestream => " [󰐿 LIVE]".to_string(),
+        ResultKind::Playlist => match r.video_count {
+            Some(count) => format!(" [󰲸 playlist, {} videos]", count),
+            None => " [󰲸 playlist]".to_string(),
+        },
+        ResultKind::Channel => match r.subscribers {
+            Some(count) => format!(" [ channel, {} subscribers]", format_count(count)),
+            None => " [ channel]".to_string(),
+        },
+    };
+    let duration = Span::styled(
+        duration,
+        Style::default().fg(if r.kind == ResultKind::Livestream {
+            Color::Rgb(237, 135, 150)
+        } else {
+            Color::Rgb(240, 198, 198)
+        }),
+    );
+    if let Some(position) = positions.get(&r.id) {
+        let watched = Span::styled(
+            format!(" [󰐊 {:.0}%]", position.progress() * 100.0),
+            Style::default().fg(Color::Rgb(138, 173, 244)),
+        );
+        return ListItem::new(Line::from(vec![
+            index, title, duration, watched, verified, uploader,
+        ]));
+    }
+    if r.kind == ResultKind::Channel {
+        let verified = Span::styled(
+            if r.is_verified { " [ verified]" } else { "" },
+            Style::default().fg(Color::Rgb(166, 218, 149)).bold(),
+        );
+        return ListItem::new(Line::from(vec![index, title, duration, verified]));
+    }
+    ListItem::new(Line::from(vec![index, title, duration, verified, uploader]))
+}
+
+/// Shortens large counts the way YouTube shows them, e.g. `1.2M`.
+fn format_count(n: i64) -> String {
+    match n {
+        n if n < 0 => "?".to_string(),
+        n if n >= 1_000_000_000 => format!("{:.1}B", n as f64 / 1e9),
+        n if n >= 1_000_000 => format!("{:.1}M", n as f64 / 1e6),
+        n if n >= 1_000 => format!("{:.1}K", n as f64 / 1e3),
+        n => n.to_string(),
+    }
+}
+
+/// Splits the list area for the preview pane: side by side when there is room, the
+/// pane below the list on narrow terminals and no pane at all when both are cramped.
+fn preview_layout(area: Rect) -> (Rect, Option<Rect>) {
+    let direction = if area.width >= 100 {
+        Direction::Horizontal
+    } else if area.height >= 24 {
+        Direction::Vertical
+    } else {
+        return (area, None);
+    };
+    let chunks = Layout::default()
+        .direction(direction)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+    (chunks[0], Some(chunks[1]))
+}
+
+/// Longest description excerpt shown in the preview pane, in characters.
+const PREVIEW_DESCRIPTION: usize = 600;
+
+/// Draws the preview pane, returns where kitty or sixel graphics should put the thumbnail.
+fn draw_preview(
+    frame: &mut Frame,
+    area: Rect,
+    result: Option<&backend::OrangeResult>,
+    preview: Option<&std::result::Result<VideoDetails, String>>,
+    thumbnail: Option<&RgbImage>,
+    graphics: Graphics,
+    cell_size: (u32, u32),
+) -> Option<Rect> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Magenta))
+        .title("Preview [P to hide]");
+    let Some(result) = result else {
+        frame.render_widget(block, area);
+        return None;
+    };
+
+    let (title, uploader, verified) = match preview {
+        Some(Ok(details)) => (
+            details.title.as_str(),
+            details.uploader.as_str(),
+            details.uploader_verified,
+        ),
+        _ => (
+            result.title.as_str(),
+            result.uploader.as_str(),
+            result.is_verified,
+        ),
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(
+            title,
+            Style::default().fg(Color::White).bold(),
+        )),
+        Line::from(vec![
+            Span::styled(
+                uploader,
+                Style::default().fg(Color::Rgb(245, 169, 127)).bold(),
+            ),
+            Span::styled(
+                if verified { " (verified)" } else { "" },
+                Style::default().fg(Color::Rgb(166, 218, 149)),
+            ),
+        ]),
+    ];
+    let info = Style::default().fg(Color::Rgb(240, 198, 198));
+    match (result.kind, preview) {
+        (ResultKind::Playlist, _) => lines.push(Line::from(Span::styled(
+            format!(
+                "Playlist | {} videos",
+                format_count(result.video_count.unwrap_or(-1))
+            ),
+            info,
+        ))),
+        (ResultKind::Channel, _) => lines.push(Line::from(Span::styled(
+            format!(
+                "Channel | {} subscribers | {} videos",
+                format_count(result.subscribers.unwrap_or(-1)),
+                format_count(result.video_count.unwrap_or(-1))
+            ),
+            info,
+        ))),
+        (_, Some(Ok(details))) => {
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "󰔛 {} | {} views | uploaded {}",
+                    backend::format_duration(details.duration),
+                    format_count(details.views),
+                    details.upload_date.as_deref().unwrap_or("?"),
+                ),
+                info,
+            )));
+            lines.push(Line::default());
+            let mut excerpt: String = details
+                .description
+                .chars()
+                .take(PREVIEW_DESCRIPTION)
+                .collect();
+            if excerpt.len() < details.description.len() {
+                excerpt.push('…');
+            }
+            lines.extend(excerpt.lines().map(|line| Line::from(line.to_string())));
+        }
+        (_, Some(Err(e))) => {
+            lines.push(Line::from(Span::styled(
+                format!("󰔛 {}", result.duration),
+                info,
+            )));
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                format!("Failed to load details: {}", e),
+                Style::default().fg(Color::Red),
+            )));
+        }
+        (_, None) => {
+            lines.push(Line::from(Span::styled(
+                format!("󰔛 {}", result.duration),
+                info,
+            )));
+            lines.push(Line::default());
+            lines.push(Line::from("Loading..."));
+        }
+    }
 
-struct App {
-    active_block: usize,
-    search_input: String,
-    footer_text: String,
-    search_cursor_position: usize,
-    results: Vec<backend::OrangeResult>,
-    selected_item: usize,
-    video_state: ListState,
-    navigating_item: usize,
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // the thumbnail takes up to half of the pane, room is kept while it loads
+    let (cell_width, cell_height) = cell_size;
+    let rows = match thumbnail {
+        Some(image) => {
+            let (_, height) = thumbnails::fit(
+                image,
+                inner.width as u32 * cell_width,
+                (inner.height / 2) as u32 * cell_height,
+            );
+            height.div_ceil(cell_height) as u16
+        }
+        None if result.thumbnail.is_some() => (inner.width * 9 / 32).min(inner.height / 2),
+        None => 0,
+    };
+    let rows = if graphics == Graphics::Off { 0 } else { rows };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(rows), Constraint::Min(0)])
+        .split(inner);
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, chunks[1]);
+
+    let image = thumbnail.filter(|_| rows > 0)?;
+    match graphics {
+        Graphics::HalfBlocks => {
+            frame.render_widget(HalfBlocks(image), chunks[0]);
+            None
+        }
+        Graphics::Kitty | Graphics::Sixel => {
+            let (width, height) = thumbnails::fit(
+                image,
+                chunks[0].width as u32 * cell_width,
+                chunks[0].height as u32 * cell_height,
+            );
+            let area = Rect {
+                width: width.div_ceil(cell_width) as u16,
+                height: height.div_ceil(cell_height) as u16,
+                ..chunks[0]
+            }
+            .intersection(chunks[0]);
+            // the terminal draws these cells, ratatui leaves them alone
+            let buf = frame.buffer_mut();
+            for y in area.top()..area.bottom() {
+                for x in area.left()..area.right() {
+                    buf.get_mut(x, y).set_skip(true);
+                }
+            }
+            Some(area)
+        }
+        Graphics::Off => None,
+    }
 }
 
-impl App {
-    fn new() -> Self {
-        let mut app = Self {
-            active_block: 1,
-            search_input: String::new(),
-            footer_text: String::new(),
-            search_cursor_position: 0,
-            results: backend::get_trending().unwrap(),
-            selected_item: 0,
-            video_state: ListState::default(),
-            navigating_item: 0,
-        };
-        if !app.results.is_empty() {
-            app.video_state.select(Some(0));
+/// Draws an image with `▀`, the upper pixel in the foreground and the lower one behind it.
+struct HalfBlocks<'a>(&'a RgbImage);
+
+impl Widget for HalfBlocks<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (width, height) = thumbnails::fit(self.0, area.width as u32, area.height as u32 * 2);
+        let image = thumbnails::scale(self.0, width, height);
+        let rgb = |pixel: &image::Rgb<u8>| Color::Rgb(pixel[0], pixel[1], pixel[2]);
+        for y in (0..height).step_by(2) {
+            for x in 0..width {
+                let top = image.get_pixel(x, y);
+                let bottom = if y + 1 < height {
+                    image.get_pixel(x, y + 1)
+                } else {
+                    top
+                };
+                buf.get_mut(area.x + x as u16, area.y + (y / 2) as u16)
+                    .set_char('▀')
+                    .set_fg(rgb(top))
+                    .set_bg(rgb(bottom));
+            }
+        }
+    }
+}
+
+/// The size of one terminal cell in pixels, guessed when the terminal does not tell.
+fn cell_size() -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            (size.width / size.columns).max(1) as u32,
+            (size.height / size.rows).max(1) as u32,
+        ),
+        _ => (8, 16),
+    }
+}
+
+fn details_lines(details: &backend::VideoDetails) -> Vec<Line<'_>> {
+    let heading = |text: &'static str| {
+        Line::from(Span::styled(
+            text,
+            Style::default().fg(Color::Rgb(198, 160, 246)).bold(),
+        ))
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            details.title.as_str(),
+            Style::default().fg(Color::White).bold(),
+        )),
+        Line::from(vec![
+            Span::styled(
+                details.uploader.as_str(),
+                Style::default().fg(Color::Rgb(245, 169, 127)).bold(),
+            ),
+            Span::styled(
+                if details.uploader_verified {
+                    " (verified)"
+                } else {
+                    ""
+                },
+                Style::default().fg(Color::Rgb(166, 218, 149)),
+            ),
+        ]),
+        Line::from(Span::styled(
+            format!(
+                "󰔛 {} | {} views | {} likes | {} dislikes | uploaded {}",
+                backend::format_duration(details.duration),
+                format_count(details.views),
+                format_count(details.likes),
+                format_count(details.dislikes),
+                details.upload_date.as_deref().unwrap_or("?"),
+            ),
+            Style::default().fg(Color::Rgb(240, 198, 198)),
+        )),
+        Line::default(),
+    ];
+
+    if !details.chapters.is_empty() {
+        lines.push(heading("Chapters"));
+        for chapter in &details.chapters {
+            lines.push(Line::from(format!(
+                "  {} {}",
+                backend::format_duration(chapter.start),
+                chapter.title
+            )));
         }
-        app
+        lines.push(Line::default());
+    }
+
+    lines.push(heading("Description"));
+    lines.extend(details.description.lines().map(Line::from));
+    lines.push(Line::default());
+
+    lines.push(heading("Streams"));
+    for stream in &details.video_streams {
+        lines.push(Line::from(format!(
+            "  video {} {} {}{}",
+            stream.quality,
+            stream.format,
+            stream.codec.as_deref().unwrap_or(""),
+            if stream.video_only {
+                ""
+            } else {
+                " (with audio)"
+            },
+        )));
+    }
+    for stream in &details.audio_streams {
+        lines.push(Line::from(format!(
+            "  audio {} {} {}",
+            stream.quality,
+            stream.format,
+            stream.codec.as_deref().unwrap_or(""),
+        )));
+    }
+    lines.push(Line::default());
+
+    if !details.related_streams.is_empty() {
+        lines.push(heading("Related"));
+        for related in &details.related_streams {
+            lines.push(Line::from(format!(
+                "  {} [{}] [{}]",
+                related.title, related.duration, related.uploader
+            )));
+        }
+    }
+
+    lines
+}
+
+fn draw_channel(
+    frame: &mut Frame,
+    area: Rect,
+    block: Block,
+    view: &mut ChannelView,
+    positions: &Positions,
+) {
+    let block = block
+        .border_style(Style::default().fg(Color::Magenta))
+        .title(format!("Channel: {} [Esc to close]", view.channel.name));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(inner);
+
+    let header = vec![
+        Line::from(vec![
+            Span::styled(
+                view.channel.name.as_str(),
+                Style::default().fg(Color::Rgb(245, 169, 127)).bold(),
+            ),
+            Span::styled(
+                if view.channel.verified {
+                    " (verified)"
+                } else {
+                    ""
+                },
+                Style::default().fg(Color::Rgb(166, 218, 149)),
+            ),
+            Span::styled(
+                format!(
+                    " | {} subscribers",
+                    format_count(view.channel.subscriber_count)
+                ),
+                Style::default().fg(Color::Rgb(240, 198, 198)),
+            ),
+        ]),
+        Line::from(view.channel.description.lines().next().unwrap_or_default()),
+    ];
+    frame.render_widget(Paragraph::new(header), chunks[0]);
+
+    let titles: Vec<String> = view.tabs.iter().map(|tab| tab.name.clone()).collect();
+    let tabs = Tabs::new(titles)
+        .select(view.active_tab)
+        .highlight_style(Style::default().fg(Color::LightGreen).bold());
+    let tabs_area = Rect {
+        y: chunks[0].y + 2,
+        height: 1,
+        ..chunks[0]
+    };
+    frame.render_widget(tabs, tabs_area);
+
+    let tab = &view.tabs[view.active_tab];
+    if tab.items.is_empty() {
+        let text = if tab.loaded {
+            "Nothing here"
+        } else {
+            "Loading..."
+        };
+        frame.render_widget(Paragraph::new(text), chunks[1]);
+        return;
+    }
+    let items: Vec<ListItem> = tab
+        .items
+        .iter()
+        .enumerate()
+        .map(|(i, r)| result_item(i, r, positions))
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+    frame.render_stateful_widget(list, chunks[1], &mut view.state);
+}
+
+fn draw_playlist(
+    frame: &mut Frame,
+    area: Rect,
+    block: Block,
+    view: &mut PlaylistView,
+    positions: &Positions,
+) {
+    let block = block
+        .border_style(Style::default().fg(Color::Magenta))
+        .title(format!(
+            "Playlist: {} by {} [{}/{}] [p to play all, Esc to close]",
+            view.playlist.name,
+            view.playlist.uploader,
+            view.state.selected().map_or(0, |i| i + 1),
+            view.playlist.video_count.max(view.items.len() as i64)
+        ));
+    if view.items.is_empty() {
+        frame.render_widget(Paragraph::new("Empty playlist").block(block), area);
+        return;
+    }
+    let items: Vec<ListItem> = view
+        .items
+        .iter()
+        .enumerate()
+        .map(|(i, r)| result_item(i, r, positions))
+        .collect();
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+    frame.render_stateful_widget(list, area, &mut view.state);
+}
+
+fn playback_gauge(playback: &PlaybackStatus) -> LineGauge<'_> {
+    let ratio = if playback.duration > 0.0 {
+        (playback.position / playback.duration).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let label = format!(
+        "{} {} {}/{} vol {:.0} {:.2}x ",
+        if playback.paused { "󰏤" } else { "󰐊" },
+        playback.title,
+        backend::format_duration(playback.position as i64),
+        backend::format_duration(playback.duration as i64),
+        playback.volume,
+        playback.speed,
+    );
+    LineGauge::default()
+        .ratio(ratio)
+        .label(label)
+        .style(Style::default().fg(Color::Rgb(240, 198, 198)))
+        .gauge_style(Style::default().fg(Color::Rgb(198, 160, 246)))
+}
+
+/// A rectangle in the middle of `area`, sized in percent of it.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
     }
 }
 
@@ -63,12 +2160,242 @@ fn main() -> Result<()> {
                 .long("url")
                 .help_heading("Play a video by url"),
         )
+        .arg(
+            Arg::new("filter")
+                .short('f')
+                .long("filter")
+                .value_parser(SearchFilter::ALL.map(|f| f.as_str()))
+                .help("Limit the search to one kind of result"),
+        )
+        .arg(
+            Arg::new("region")
+                .short('r')
+                .long("region")
+                .help("Country code for the trending page, e.g. US or DE"),
+        )
+        .arg(
+            Arg::new("player")
+                .short('p')
+                .long("player")
+                .help("mpv, vlc or a command template like \"celluloid {url}\""),
+        )
+        .arg(
+            Arg::new("audio")
+                .short('a')
+                .long("audio")
+                .action(ArgAction::SetTrue)
+                .help("Play without video, for music and talks"),
+        )
+        .arg(
+            Arg::new("quality")
+                .long("quality")
+                .help("Stream quality, e.g. \"<=1080p, prefer av1\""),
+        )
+        .arg(
+            Arg::new("stream-source")
+                .long("stream-source")
+                .value_parser(["youtube", "piped"])
+                .help("Play the youtube url through yt-dlp or the streams proxied by Piped"),
+        )
+        .arg(
+            Arg::new("download")
+                .short('d')
+                .long("download")
+                .help("Download a video by url or id, --audio saves only the audio"),
+        )
+        .arg(
+            Arg::new("subs")
+                .long("subs")
+                .requires("download")
+                .help("Save subtitles next to the download, languages like \"en,de\""),
+        )
+        .arg(
+            Arg::new("instance")
+                .short('i')
+                .long("instance")
+                .action(ArgAction::Append)
+                .help("Piped API instance to use, can be given multiple times"),
+        )
         .get_matches();
 
     let url_is_not_empty = matches.get_one::<String>("url").is_some();
     let search_is_empty = matches.get_one::<String>("search").is_some().not();
 
-    let mut app = App::new();
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            println!("Failed to load config: {}", e);
+            return Ok(());
+        }
+    };
+    let instances = match matches.get_many::<String>("instance") {
+        Some(instances) => instances.cloned().collect(),
+        None => config.instances,
+    };
+
+    let region = matches
+        .get_one::<String>("region")
+        .cloned()
+        .or(config.region)
+        .unwrap_or_else(|| backend::DEFAULT_REGION.to_string());
+    let region = match backend::validate_region(&region) {
+        Ok(region) => region,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+
+    let quality = match matches
+        .get_one::<String>("quality")
+        .cloned()
+        .or(config.quality)
+        .map(|q| q.parse::<QualityPreference>())
+        .transpose()
+    {
+        Ok(quality) => quality,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+
+    let stream_source = match matches
+        .get_one::<String>("stream-source")
+        .cloned()
+        .or(config.stream_source)
+        .map(|s| s.parse::<StreamSource>())
+        .transpose()
+    {
+        Ok(stream_source) => stream_source.unwrap_or_default(),
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+
+    let graphics = match config
+        .thumbnails
+        .as_deref()
+        .unwrap_or("auto")
+        .parse::<Graphics>()
+    {
+        Ok(graphics) => graphics,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+
+    let installed = player::detect_installed();
+    let player = matches
+        .get_one::<String>("player")
+        .cloned()
+        .or(config.player)
+        .or_else(|| installed.first().map(|name| name.to_string()))
+        .unwrap_or_else(|| player::BUILTIN[0].to_string());
+    let player = player::from_name(&player);
+
+    let client = backend::PipedClient::new(backend::Instances::new(instances));
+    let downloader = Downloader::new(
+        config
+            .download_dir
+            .or_else(dirs::download_dir)
+            .unwrap_or_else(|| PathBuf::from(".")),
+        config
+            .filename_template
+            .unwrap_or_else(|| download::DEFAULT_TEMPLATE.to_string()),
+        config
+            .max_downloads
+            .unwrap_or(download::DEFAULT_CONCURRENCY),
+    );
+    let mut app = App::new(client, Runtime::new()?, region, player, downloader);
+    if !app.player.is_installed() {
+        app.footer_text = format!(
+            "{} is not installed, installed players: {}",
+            app.player.name(),
+            if installed.is_empty() {
+                "none".to_string()
+            } else {
+                installed.join(", ")
+            }
+        );
+    }
+    if let Some(filter) = matches.get_one::<String>("filter") {
+        app.filter = filter.parse().unwrap();
+    }
+    app.play_options.audio_only = matches.get_flag("audio");
+    app.quality = quality;
+    match Positions::load() {
+        Ok(positions) => app.positions = positions,
+        Err(e) => app.footer_text = format!("Failed to load positions: {}", e),
+    }
+    app.stream_source = stream_source;
+    app.graphics = graphics;
+    app.subtitle_languages = config.subtitles;
+    if let Some(categories) = config.sponsorblock {
+        app.sponsor_categories = categories;
+    }
+
+    if let Some(url) = matches.get_one::<String>("download") {
+        let id = backend::video_id(url).unwrap_or(url);
+        let details = match app.runtime.block_on(app.client.video_details(id)) {
+            Ok(details) => details,
+            Err(e) => {
+                println!("Failed to load streams: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let quality = app.quality.clone().unwrap_or_default();
+        let jobs = app
+            .downloader
+            .jobs(&details, &quality, app.play_options.audio_only);
+        if jobs.is_empty() {
+            println!("No downloadable stream for {}", details.title);
+            std::process::exit(1);
+        }
+        let video_path = jobs[0].path.clone();
+        for job in jobs {
+            println!("Downloading to {}", job.path.display());
+            let result = app.runtime.block_on(app.downloader.download(
+                &job.url,
+                &job.path,
+                |downloaded, total| {
+                    if let Some(total) = total.filter(|total| *total > 0) {
+                        print!("\r{:.1}%", downloaded as f64 * 100.0 / total as f64);
+                        stdout().flush().ok();
+                    }
+                },
+            ));
+            println!();
+            if let Err(e) = result {
+                println!("Failed to download: {}", e);
+                std::process::exit(1);
+            }
+        }
+        if let Some(languages) = matches.get_one::<String>("subs") {
+            let languages: Vec<String> =
+                languages.split(',').map(|l| l.trim().to_string()).collect();
+            let Some(subtitle) = subtitles::pick(&details.subtitles, &languages) else {
+                println!("No subtitles in {}", languages.join(", "));
+                std::process::exit(1);
+            };
+            let path = subtitles::srt_path(&video_path, &subtitle.code);
+            let saved = app
+                .runtime
+                .block_on(app.client.subtitle(subtitle))
+                .map_err(|e| e.to_string())
+                .and_then(|srt| fs::write(&path, srt).map_err(|e| e.to_string()));
+            match saved {
+                Ok(()) => println!("Saved subtitles to {}", path.display()),
+                Err(e) => {
+                    println!("Failed to download subtitles: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        return Ok(());
+    }
 
     if url_is_not_empty && search_is_empty.not() {
         println!("Please provide either a search query or a video url, not both.");
@@ -78,8 +2405,37 @@ fn main() -> Result<()> {
     if url_is_not_empty {
         let url = matches.get_one::<String>("url").unwrap();
         println!("Playing from: {}", url);
-        backend::play_url(&url);
-        return Ok(());
+        let mut playback = (url.to_string(), app.play_options.clone());
+        let from_piped = app.quality.is_some() || app.stream_source == StreamSource::Piped;
+        if let Some(id) = backend::video_id(url).filter(|_| from_piped) {
+            match app.runtime.block_on(app.client.video_details(id)) {
+                Ok(details) => {
+                    if let Some(streams) = piped_playback(
+                        &details,
+                        app.quality.as_ref(),
+                        &app.play_options,
+                        app.player.separate_audio(),
+                    ) {
+                        playback = streams;
+                    }
+                }
+                Err(e) => println!("Failed to load streams, playing the url instead: {}", e),
+            }
+        }
+        match app.player.play_blocking(&[playback.0], &playback.1) {
+            Ok(outcome) if outcome.success() => return Ok(()),
+            Ok(outcome) => {
+                println!("{}", outcome);
+                for line in &outcome.stderr {
+                    eprintln!("{}", line);
+                }
+                std::process::exit(outcome.code());
+            }
+            Err(e) => {
+                println!("Failed to play: {}", e);
+                std::process::exit(1);
+            }
+        }
     }
 
     if !search_is_empty {
@@ -87,7 +2443,9 @@ fn main() -> Result<()> {
             .get_one::<String>("search")
             .map(|s| s.to_string())
             .unwrap();
-        app.results = backend::get_search(&search).unwrap();
+        app.search(&search);
+    } else {
+        app.start_trending();
     }
 
     stdout().execute(EnterAlternateScreen)?;
@@ -95,7 +2453,9 @@ fn main() -> Result<()> {
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
     terminal.clear()?;
     loop {
+        let overlay = app.has_overlay();
         terminal.draw(|frame| {
+            app.thumbnail_area = None;
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints(
@@ -114,7 +2474,9 @@ fn main() -> Result<()> {
                     .border_type(BorderType::Rounded);
                 match i {
                     0 => {
-                        let block = block.title("Search").style(Style::default().fg(
+                        let block = block
+                            .title(format!("Search [filter: {}]", app.filter))
+                            .style(Style::default().fg(
                             if 0 == app.active_block {
                                 Color::LightGreen
                             } else {
@@ -139,63 +2501,93 @@ fn main() -> Result<()> {
                         frame.render_widget(paragraph, *chunk);
                     }
                     1 => {
-                        if app.results.len() > 0 {
+                        let (area, preview_area) = if app.preview && app.details.is_none() {
+                            preview_layout(*chunk)
+                        } else {
+                            (*chunk, None)
+                        };
+                        if let Some(details) = &app.details {
+                            let block = block
+                                .border_style(Style::default().fg(Color::Magenta))
+                                .title("Details [Esc to close]");
+                            let paragraph = Paragraph::new(details_lines(details))
+                                .block(block)
+                                .wrap(Wrap { trim: false })
+                                .scroll((app.details_scroll, 0));
+                            frame.render_widget(paragraph, area);
+                        } else if let Some(view) = &mut app.playlist {
+                            draw_playlist(frame, area, block, view, &app.positions);
+                        } else if let Some(view) = &mut app.channel {
+                            draw_channel(frame, area, block, view, &app.positions);
+                        } else if !app.results.is_empty() {
                             let block = block
                                 .border_style(Style::default().fg(Color::Magenta))
                                 .title(format!(
-                                    "Videos [{}/{}]",
+                                    "{}{} [{}/{}{}]",
+                                    if app.session.is_some() {
+                                        "Results".to_string()
+                                    } else {
+                                        format!("Trending ({})", app.region)
+                                    },
+                                    if app.play_options.audio_only {
+                                        " (audio only)"
+                                    } else {
+                                        ""
+                                    },
                                     app.navigating_item + 1,
-                                    app.results.len()
+                                    app.results.len(),
+                                    if app.session.as_ref().is_some_and(|s| !s.is_exhausted()) {
+                                        "+"
+                                    } else {
+                                        ""
+                                    }
                                 ));
                             let items: Vec<ListItem> = app
                                 .results
                                 .iter()
                                 .enumerate()
-                                .map(|(i, r)| {
-                                    let index = Span::styled(
-                                        format!("{}. ", i + 1),
-                                        Style::default().fg(Color::Rgb(198, 160, 246)),
-                                    );
-                                    let title = Span::styled(
-                                        format!("{}", r.title),
-                                        Style::default().fg(Color::White).bold(),
-                                    );
-                                    let verified = Span::styled(
-                                        format!(" [ "),
-                                        Style::default()
-                                            .fg(if r.is_verified {
-                                                Color::Rgb(166, 218, 149)
-                                            } else {
-                                                Color::Rgb(237, 135, 150)
-                                            })
-                                            .bold(),
-                                    );
-                                    let uploader = Span::styled(
-                                        format!("{}]", r.uploader),
-                                        Style::default().fg(Color::Rgb(245, 169, 127)).bold(),
-                                    );
-                                    let duration = Span::styled(
-                                        format!(" [󰔛 {}]", r.duration),
-                                        Style::default().fg(Color::Rgb(240, 198, 198)),
-                                    );
-                                    ListItem::new(Line::from(vec![
-                                        index, title, duration, verified, uploader,
-                                    ]))
-                                })
+                                .map(|(i, r)| result_item(i, r, &app.positions))
                                 .collect::<Vec<ListItem>>();
 
                             let list = List::new(items).block(block).highlight_style(
                                 Style::default().bg(Color::White).fg(Color::Black),
                             );
-                            frame.render_stateful_widget(list, *chunk, &mut app.video_state);
+                            frame.render_stateful_widget(list, area, &mut app.video_state);
                         } else {
                             let block = block
                                 .border_style(Style::default().fg(Color::Magenta))
                                 .title("No Videos");
-                            let paragraph = Paragraph::new("No results found")
+                            let paragraph = Paragraph::new(if app.is_loading() {
+                                "Loading..."
+                            } else {
+                                "No results found"
+                            })
                                 .block(block)
                                 .wrap(Wrap { trim: true });
-                            frame.render_widget(paragraph, *chunk);
+                            frame.render_widget(paragraph, area);
+                        }
+                        if let Some(preview_area) = preview_area {
+                            let highlighted = app.highlighted();
+                            let preview = highlighted.and_then(|r| app.previews.get(&r.id));
+                            let thumbnail = highlighted
+                                .and_then(|r| app.thumbnails.get(&r.id))
+                                .and_then(|thumbnail| thumbnail.as_ref().ok());
+                            // popups would end up under kitty or sixel graphics
+                            let graphics = match app.graphics {
+                                Graphics::Kitty | Graphics::Sixel if overlay => {
+                                    Graphics::HalfBlocks
+                                }
+                                graphics => graphics,
+                            };
+                            app.thumbnail_area = draw_preview(
+                                frame,
+                                preview_area,
+                                highlighted,
+                                preview,
+                                thumbnail,
+                                graphics,
+                                app.cell_size,
+                            );
                         }
                     }
                     2 => {
@@ -206,30 +2598,320 @@ fn main() -> Result<()> {
                                 Color::White
                             },
                         ));
-                        let footer_text = if app.footer_text.is_empty() {
+                        let footer_text = if app.footer_text.is_empty() && app.playback.is_some() {
                             Span::styled(
-                                format!("Press / to search | Press q to quit"),
+                                "space pause | ←/→ seek | 9/0 volume | [/] speed | s stop"
+                                    .to_string(),
                                 Style::default().fg(Color::Red),
                             )
-                        } else {
+                        } else if app.footer_text.is_empty() {
                             Span::styled(
-                                format!("{}", app.footer_text.clone()),
-                                Style::default().fg(Color::Green),
+                                "Press / to search | Press i for details | Press c for channel | Press f to filter | Press a for audio only | Press v for quality | Press e to enqueue | Press o for queue | Press d to download | Press t for subtitles | Press r for region | Press P for preview | Press q to quit"
+                                    .to_string(),
+                                Style::default().fg(Color::Red),
                             )
+                        } else {
+                            Span::styled(app.footer_text.clone(), Style::default().fg(Color::Green))
                         };
-                        let paragraph = Paragraph::new(footer_text)
-                            .block(block)
-                            .wrap(Wrap { trim: true });
-                        frame.render_widget(paragraph, *chunk);
+                        let mut status = vec![];
+                        if app.is_loading() {
+                            let frame = app.started_at.elapsed().as_millis()
+                                / SPINNER_FRAME.as_millis();
+                            status.push(Span::styled(
+                                format!("{} ", SPINNER[frame as usize % SPINNER.len()]),
+                                Style::default().fg(Color::Rgb(198, 160, 246)),
+                            ));
+                        }
+                        status.push(footer_text);
+                        let running: Vec<&DownloadState> =
+                            app.downloads.iter().filter(|d| d.running).collect();
+                        if !running.is_empty() {
+                            let downloaded: u64 = running.iter().map(|d| d.downloaded).sum();
+                            let total: u64 = running.iter().filter_map(|d| d.total).sum();
+                            status.push(Span::styled(
+                                if total > 0 {
+                                    format!(
+                                        " | 󰇚 {} {:.0}%",
+                                        running.len(),
+                                        downloaded as f64 * 100.0 / total as f64
+                                    )
+                                } else {
+                                    format!(" | 󰇚 {}", running.len())
+                                },
+                                Style::default().fg(Color::DarkGray),
+                            ));
+                        }
+                        if !app.queue.is_empty() {
+                            status.push(Span::styled(
+                                format!(" | {} queued", app.queue.len()),
+                                Style::default().fg(Color::DarkGray),
+                            ));
+                        }
+                        if let Some(instance) = app.client.instances().last_used() {
+                            status.push(Span::styled(
+                                format!(" | via {}", backend::instance_name(&instance)),
+                                Style::default().fg(Color::DarkGray),
+                            ));
+                        }
+                        let paragraph =
+                            Paragraph::new(Line::from(status)).wrap(Wrap { trim: true });
+                        let inner = block.inner(*chunk);
+                        frame.render_widget(block, *chunk);
+                        match &app.playback {
+                            Some(playback) => {
+                                let halves = Layout::default()
+                                    .direction(Direction::Horizontal)
+                                    .constraints([
+                                        Constraint::Percentage(50),
+                                        Constraint::Percentage(50),
+                                    ])
+                                    .split(inner);
+                                frame.render_widget(playback_gauge(playback), halves[0]);
+                                frame.render_widget(paragraph, halves[1]);
+                            }
+                            None => frame.render_widget(paragraph, inner),
+                        }
                     }
                     _ => {}
                 };
             }
+
+            if let Some(state) = &mut app.region_picker {
+                let area = centered_rect(30, 60, frame.size());
+                let items: Vec<ListItem> = backend::REGIONS
+                    .iter()
+                    .map(|region| ListItem::new(*region))
+                    .collect();
+                let list = List::new(items)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded)
+                            .border_style(Style::default().fg(Color::Magenta))
+                            .title("Trending region [Enter to pick, Esc to close]"),
+                    )
+                    .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+                frame.render_widget(Clear, area);
+                frame.render_stateful_widget(list, area, state);
+            }
+
+            if let Some((error, _)) = app.results_error.as_ref().filter(|_| !app.is_loading()) {
+                let area = centered_rect(50, 25, frame.size());
+                let text = vec![
+                    Line::from(Span::from(error.as_str()).bold()),
+                    Line::from(""),
+                    Line::from(if app.results.is_empty() {
+                        "Check the network or the instances in the config."
+                    } else {
+                        "The list shows what was loaded before."
+                    }),
+                ];
+                let paragraph = Paragraph::new(text).wrap(Wrap { trim: true }).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(Color::Red))
+                        .title("Error [R to retry, Esc to close]"),
+                );
+                frame.render_widget(Clear, area);
+                frame.render_widget(paragraph, area);
+            }
+
+            if let Some((toast, _)) = &app.toast {
+                let size = frame.size();
+                let width = (toast.chars().count() as u16 + 4).min(size.width);
+                let area = Rect {
+                    x: size.width.saturating_sub(width + 1),
+                    y: 1,
+                    width,
+                    height: 3.min(size.height),
+                };
+                let paragraph = Paragraph::new(toast.as_str())
+                    .style(Style::default().fg(Color::Rgb(166, 218, 149)))
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    );
+                frame.render_widget(Clear, area);
+                frame.render_widget(paragraph, area);
+            }
+
+            if let Some(prompt) = &app.resume_prompt {
+                let area = centered_rect(50, 20, frame.size());
+                let text = vec![
+                    Line::from(Span::from(prompt.title.as_str()).bold()),
+                    Line::from(format!(
+                        "Resume from {} of {}?",
+                        backend::format_duration(prompt.position.position as i64),
+                        backend::format_duration(prompt.position.duration as i64),
+                    )),
+                ];
+                let paragraph = Paragraph::new(text).wrap(Wrap { trim: true }).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(Color::Magenta))
+                        .title("Resume [y resume, n start over, Esc cancel]"),
+                );
+                frame.render_widget(Clear, area);
+                frame.render_widget(paragraph, area);
+            }
+
+            if let Some(state) = &mut app.queue_panel {
+                let area = centered_rect(60, 60, frame.size());
+                let items: Vec<ListItem> = app
+                    .queue
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        ListItem::new(format!(
+                            "{}. {} [{}] [{}]",
+                            i + 1,
+                            item.title,
+                            item.duration,
+                            item.uploader
+                        ))
+                    })
+                    .collect();
+                let list = List::new(items)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded)
+                            .border_style(Style::default().fg(Color::Magenta))
+                            .title(format!(
+                                "Queue ({}) [Enter play, K/J move, d remove, C clear, Esc close]",
+                                app.queue.len()
+                            )),
+                    )
+                    .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+                frame.render_widget(Clear, area);
+                frame.render_stateful_widget(list, area, state);
+            }
+
+            if let Some(picker) = &mut app.subtitle_picker {
+                let area = centered_rect(50, 60, frame.size());
+                let items: Vec<ListItem> = std::iter::once(ListItem::new("Off"))
+                    .chain(picker.subtitles.iter().map(|s| {
+                        ListItem::new(format!(
+                            "{} ({}){}",
+                            s.name,
+                            s.code,
+                            if s.auto_generated {
+                                " auto-generated"
+                            } else {
+                                ""
+                            }
+                        ))
+                    }))
+                    .collect();
+                let list = List::new(items)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded)
+                            .border_style(Style::default().fg(Color::Magenta))
+                            .title(format!(
+                                "Subtitles: {} [Enter to play, Esc to close]",
+                                picker.title
+                            )),
+                    )
+                    .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+                frame.render_widget(Clear, area);
+                frame.render_stateful_widget(list, area, &mut picker.state);
+            }
+
+            if let Some(picker) = &mut app.quality_picker {
+                let area = centered_rect(50, 60, frame.size());
+                let auto = match &app.quality {
+                    Some(quality) => format!("Auto ({})", quality),
+                    None => "Auto (best)".to_string(),
+                };
+                let items: Vec<ListItem> = std::iter::once(ListItem::new(auto))
+                    .chain(
+                        picker
+                            .video_streams
+                            .iter()
+                            .map(|s| ListItem::new(format!("{} {}fps", s.label(), s.fps))),
+                    )
+                    .collect();
+                let list = List::new(items)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded)
+                            .border_style(Style::default().fg(Color::Magenta))
+                            .title(format!("Quality: {} [Enter to play, Esc to close]", picker.title)),
+                    )
+                    .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+                frame.render_widget(Clear, area);
+                frame.render_stateful_widget(list, area, &mut picker.state);
+            }
+
+            if app.active_block == 0 && !app.suggestions.is_empty() {
+                let area = Rect {
+                    x: chunks[0].x + 1,
+                    y: chunks[0].bottom(),
+                    width: chunks[0].width.saturating_sub(2),
+                    height: (app.suggestions.len() as u16 + 2)
+                        .min(frame.size().height.saturating_sub(chunks[0].bottom())),
+                };
+                let items: Vec<ListItem> = app
+                    .suggestions
+                    .iter()
+                    .map(|s| ListItem::new(s.as_str()))
+                    .collect();
+                let list = List::new(items)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded)
+                            .border_style(Style::default().fg(Color::LightGreen))
+                            .title("Suggestions [Tab to accept]"),
+                    )
+                    .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+                frame.render_widget(Clear, area);
+                frame.render_stateful_widget(list, area, &mut app.suggestion_state);
+            }
         })?;
+        app.show_thumbnail()?;
 
-        if event::poll(std::time::Duration::from_millis(16))? {
+        app.update_tasks();
+        app.update_preview();
+        app.update_suggestions();
+        app.update_downloads();
+        app.update_segments();
+        app.poll_player();
+        app.poll_playback();
+
+        if event::poll(Duration::from_millis(16))? {
             if let Event::Key(key) = event::read()? {
                 match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter if app.resume_prompt.is_some() => {
+                        let prompt = app.resume_prompt.take().unwrap();
+                        app.play_video_at(
+                            &prompt.id,
+                            &prompt.title,
+                            Some(prompt.position.position),
+                        );
+                    }
+                    KeyCode::Char('n') if app.resume_prompt.is_some() => {
+                        let prompt = app.resume_prompt.take().unwrap();
+                        app.positions.remove(&prompt.id);
+                        app.play_video_at(&prompt.id, &prompt.title, None);
+                    }
+                    KeyCode::Esc if app.resume_prompt.is_some() => {
+                        app.resume_prompt = None;
+                        app.subtitle_choice = None;
+                        if app.queue_running {
+                            app.queue_running = false;
+                            app.footer_text = format!(
+                                "Queue paused, {} left | Enter in the queue panel plays on",
+                                app.queue.len()
+                            );
+                        }
+                    }
                     KeyCode::Char('/') => {
                         app.active_block = 0;
                         app.search_cursor_position = app.search_input.len();
@@ -237,12 +2919,35 @@ fn main() -> Result<()> {
                     KeyCode::Char(c) if app.active_block == 0 => {
                         app.search_input.insert(app.search_cursor_position, c);
                         app.search_cursor_position += 1;
+                        app.search_edited();
                     }
                     KeyCode::Backspace
                         if app.active_block == 0 && app.search_cursor_position > 0 =>
                     {
                         app.search_input.remove(app.search_cursor_position - 1);
                         app.search_cursor_position -= 1;
+                        app.search_edited();
+                    }
+                    KeyCode::Up if app.active_block == 0 && !app.suggestions.is_empty() => {
+                        let selected = match app.suggestion_state.selected() {
+                            Some(0) | None => None,
+                            Some(i) => Some(i - 1),
+                        };
+                        app.suggestion_state.select(selected);
+                    }
+                    KeyCode::Down if app.active_block == 0 && !app.suggestions.is_empty() => {
+                        list_move(&mut app.suggestion_state, app.suggestions.len(), false);
+                    }
+                    KeyCode::Tab
+                        if app.active_block == 0 && app.suggestion_state.selected().is_some() =>
+                    {
+                        let i = app.suggestion_state.selected().unwrap();
+                        app.search_input = app.suggestions[i].clone();
+                        app.search_cursor_position = app.search_input.len();
+                        app.clear_suggestions();
+                    }
+                    KeyCode::Esc if app.active_block == 0 && !app.suggestions.is_empty() => {
+                        app.clear_suggestions();
                     }
                     KeyCode::Left if app.active_block == 0 && app.search_cursor_position > 0 => {
                         app.search_cursor_position -= 1;
@@ -253,38 +2958,311 @@ fn main() -> Result<()> {
                     {
                         app.search_cursor_position += 1;
                     }
-                    KeyCode::Up if app.active_block == 1 => {
-                        if app.results.len() > 0 && app.navigating_item > 0 {
-                            app.video_state.select(Some(app.navigating_item - 1));
-                            app.navigating_item -= 1;
+                    KeyCode::Esc | KeyCode::Char('o') if app.queue_panel.is_some() => {
+                        app.queue_panel = None;
+                    }
+                    KeyCode::Up | KeyCode::Down if app.queue_panel.is_some() => {
+                        let state = app.queue_panel.as_mut().unwrap();
+                        list_move(state, app.queue.len(), key.code == KeyCode::Up);
+                    }
+                    KeyCode::Char('K') | KeyCode::Char('J') if app.queue_panel.is_some() => {
+                        let state = app.queue_panel.as_mut().unwrap();
+                        if let Some(i) = state.selected() {
+                            if key.code == KeyCode::Char('K') && app.queue.move_up(i) {
+                                state.select(Some(i - 1));
+                            } else if key.code == KeyCode::Char('J') && app.queue.move_down(i) {
+                                state.select(Some(i + 1));
+                            }
+                        }
+                    }
+                    KeyCode::Char('d') | KeyCode::Delete if app.queue_panel.is_some() => {
+                        let state = app.queue_panel.as_mut().unwrap();
+                        if let Some(i) = state.selected() {
+                            app.queue.remove(i);
+                            state.select(match app.queue.len() {
+                                0 => None,
+                                len => Some(i.min(len - 1)),
+                            });
+                        }
+                    }
+                    KeyCode::Char('C') if app.queue_panel.is_some() => {
+                        app.queue.clear();
+                        app.queue_panel = Some(ListState::default());
+                    }
+                    KeyCode::Enter if app.queue_panel.is_some() => {
+                        let state = app.queue_panel.as_mut().unwrap();
+                        if let Some(item) = state.selected().and_then(|i| app.queue.remove(i)) {
+                            state.select(match app.queue.len() {
+                                0 => None,
+                                len => state.selected().map(|i| i.min(len - 1)),
+                            });
+                            app.queue_running = true;
+                            app.play_video(&item.id, &item.title);
+                        }
+                    }
+                    KeyCode::Esc if app.subtitle_picker.is_some() => {
+                        app.subtitle_picker = None;
+                    }
+                    KeyCode::Up | KeyCode::Down if app.subtitle_picker.is_some() => {
+                        let picker = app.subtitle_picker.as_mut().unwrap();
+                        list_move(
+                            &mut picker.state,
+                            picker.subtitles.len() + 1,
+                            key.code == KeyCode::Up,
+                        );
+                    }
+                    KeyCode::Enter if app.subtitle_picker.is_some() => {
+                        let mut picker = app.subtitle_picker.take().unwrap();
+                        let choice = match picker.state.selected() {
+                            Some(0) | None => None,
+                            Some(i) => Some(picker.subtitles.swap_remove(i - 1)),
+                        };
+                        app.subtitle_choice = Some(choice);
+                        app.play_video(&picker.id, &picker.title);
+                    }
+                    KeyCode::Char('t')
+                        if app.active_block == 1
+                            && (app.details.is_some()
+                                || app.highlighted().is_some_and(|r| r.kind.is_video())) =>
+                    {
+                        app.open_subtitle_picker();
+                    }
+                    KeyCode::Esc if app.quality_picker.is_some() => {
+                        app.quality_picker = None;
+                    }
+                    KeyCode::Up | KeyCode::Down if app.quality_picker.is_some() => {
+                        let picker = app.quality_picker.as_mut().unwrap();
+                        list_move(
+                            &mut picker.state,
+                            picker.video_streams.len() + 1,
+                            key.code == KeyCode::Up,
+                        );
+                    }
+                    KeyCode::Enter if app.quality_picker.is_some() => {
+                        let picker = app.quality_picker.take().unwrap();
+                        let video = match picker.state.selected() {
+                            Some(0) | None => app
+                                .quality
+                                .clone()
+                                .unwrap_or_default()
+                                .pick(&picker.video_streams),
+                            Some(i) => picker.video_streams.get(i - 1),
+                        };
+                        app.play_streams(&picker.id, &picker.title, video, &picker.audio_streams);
+                    }
+                    KeyCode::Char('v')
+                        if app.active_block == 1
+                            && (app.details.is_some()
+                                || app.highlighted().is_some_and(|r| r.kind.is_video())) =>
+                    {
+                        app.open_quality_picker();
+                    }
+                    KeyCode::Char('P') if app.active_block != 0 => {
+                        app.preview = !app.preview;
+                    }
+                    KeyCode::Char('R') if app.active_block != 0 && app.results_error.is_some() => {
+                        app.retry();
+                    }
+                    KeyCode::Esc if app.active_block != 0 && app.results_error.is_some() => {
+                        app.results_error = None;
+                    }
+                    KeyCode::Esc if app.region_picker.is_some() => {
+                        app.region_picker = None;
+                    }
+                    KeyCode::Up | KeyCode::Down if app.region_picker.is_some() => {
+                        let state = app.region_picker.as_mut().unwrap();
+                        list_move(state, backend::REGIONS.len(), key.code == KeyCode::Up);
+                    }
+                    KeyCode::Enter if app.region_picker.is_some() => {
+                        let state = app.region_picker.take().unwrap();
+                        if let Some(i) = state.selected() {
+                            app.load_trending(backend::REGIONS[i]);
+                        }
+                    }
+                    KeyCode::Char('r') if app.active_block == 1 => {
+                        let mut state = ListState::default();
+                        state.select(backend::REGIONS.iter().position(|r| *r == app.region));
+                        app.region_picker = Some(state);
+                    }
+                    KeyCode::Esc | KeyCode::Char('i') if app.details.is_some() => {
+                        app.details = None;
+                    }
+                    KeyCode::Up if app.details.is_some() => {
+                        app.details_scroll = app.details_scroll.saturating_sub(1);
+                    }
+                    KeyCode::Down if app.details.is_some() => {
+                        app.details_scroll = app.details_scroll.saturating_add(1);
+                    }
+                    KeyCode::PageUp if app.details.is_some() => {
+                        app.details_scroll = app.details_scroll.saturating_sub(10);
+                    }
+                    KeyCode::PageDown if app.details.is_some() => {
+                        app.details_scroll = app.details_scroll.saturating_add(10);
+                    }
+                    KeyCode::Esc if app.playlist.is_some() => {
+                        app.playlist = None;
+                        app.footer_text.clear();
+                    }
+                    KeyCode::Up if app.playlist.is_some() => {
+                        let view = app.playlist.as_mut().unwrap();
+                        list_move(&mut view.state, view.items.len(), true);
+                    }
+                    KeyCode::Down if app.playlist.is_some() => {
+                        let view = app.playlist.as_mut().unwrap();
+                        if !list_move(&mut view.state, view.items.len(), false) {
+                            app.load_playlist_page();
+                        }
+                    }
+                    KeyCode::Char('p') if app.active_block == 1 && app.playlist.is_some() => {
+                        app.play_playlist();
+                    }
+                    KeyCode::Esc if app.channel.is_some() => {
+                        app.channel = None;
+                        app.footer_text.clear();
+                    }
+                    KeyCode::Left | KeyCode::Right if app.channel.is_some() => {
+                        let view = app.channel.as_mut().unwrap();
+                        let count = view.tabs.len();
+                        view.active_tab = if key.code == KeyCode::Left {
+                            (view.active_tab + count - 1) % count
+                        } else {
+                            (view.active_tab + 1) % count
+                        };
+                        view.state
+                            .select((!view.current().items.is_empty()).then_some(0));
+                        if !view.current().loaded {
+                            app.load_channel_page();
+                        }
+                    }
+                    KeyCode::Up if app.channel.is_some() => {
+                        let view = app.channel.as_mut().unwrap();
+                        let len = view.current().items.len();
+                        list_move(&mut view.state, len, true);
+                    }
+                    KeyCode::Down if app.channel.is_some() => {
+                        let view = app.channel.as_mut().unwrap();
+                        let len = view.current().items.len();
+                        if !list_move(&mut view.state, len, false) {
+                            app.load_channel_page();
                         }
                     }
-                    KeyCode::Down if app.active_block == 1 => {
-                        if app.results.len() > 0 && app.navigating_item < app.results.len() - 1 {
-                            app.video_state.select(Some(app.navigating_item + 1));
-                            app.navigating_item += 1;
+                    KeyCode::Char(' ') if app.active_block != 0 && app.ipc.is_some() => {
+                        app.control(|ipc| ipc.toggle_pause());
+                    }
+                    KeyCode::Left | KeyCode::Right
+                        if app.active_block == 1 && app.ipc.is_some() =>
+                    {
+                        let step = if key.code == KeyCode::Left {
+                            -SEEK_STEP
+                        } else {
+                            SEEK_STEP
+                        };
+                        app.control(|ipc| ipc.seek(step));
+                    }
+                    KeyCode::Char('9') | KeyCode::Char('0')
+                        if app.active_block != 0 && app.ipc.is_some() =>
+                    {
+                        let step = if key.code == KeyCode::Char('9') {
+                            -VOLUME_STEP
+                        } else {
+                            VOLUME_STEP
+                        };
+                        app.control(|ipc| ipc.add_volume(step));
+                    }
+                    KeyCode::Char('[') | KeyCode::Char(']')
+                        if app.active_block != 0 && app.ipc.is_some() =>
+                    {
+                        let factor = if key.code == KeyCode::Char('[') {
+                            1.0 / SPEED_STEP
+                        } else {
+                            SPEED_STEP
+                        };
+                        app.control(|ipc| ipc.multiply_speed(factor));
+                    }
+                    KeyCode::Char('s') if app.active_block != 0 && app.child.is_some() => {
+                        app.queue_running = false;
+                        app.stop_player();
+                        app.footer_text = "Stopped".to_string();
+                    }
+                    KeyCode::Char('e') if app.active_block == 1 => app.enqueue(),
+                    KeyCode::Char('d') if app.active_block == 1 => app.download(),
+                    KeyCode::Char('o') if app.active_block != 0 => {
+                        let mut state = ListState::default();
+                        state.select((!app.queue.is_empty()).then_some(0));
+                        app.queue_panel = Some(state);
+                    }
+                    KeyCode::Char('a') if app.active_block != 0 => {
+                        app.play_options.audio_only = !app.play_options.audio_only;
+                        app.footer_text = format!(
+                            "Audio only: {}",
+                            if app.play_options.audio_only {
+                                "on"
+                            } else {
+                                "off"
+                            }
+                        );
+                    }
+                    KeyCode::Char('c')
+                        if app.active_block == 1
+                            && app.channel.is_none()
+                            && app.playlist.is_none() =>
+                    {
+                        app.open_channel();
+                    }
+                    KeyCode::Char('i')
+                        if app.active_block == 1
+                            && app.highlighted().is_some_and(|r| r.kind.is_video()) =>
+                    {
+                        let id = app.highlighted().unwrap().id.clone();
+                        app.open_details(&id);
+                    }
+                    KeyCode::Char('f') if app.active_block == 1 => {
+                        app.filter = app.filter.next();
+                        match app.session.as_ref().map(|s| s.query().to_string()) {
+                            Some(query) => app.search(&query),
+                            None => app.footer_text = format!("Search filter: {}", app.filter),
                         }
                     }
+                    KeyCode::Up if app.active_block == 1 && app.navigating_item > 0 => {
+                        app.video_state.select(Some(app.navigating_item - 1));
+                        app.navigating_item -= 1;
+                    }
+                    KeyCode::Down
+                        if app.active_block == 1 && app.navigating_item + 1 < app.results.len() =>
+                    {
+                        app.video_state.select(Some(app.navigating_item + 1));
+                        app.navigating_item += 1;
+                        app.load_more_results();
+                    }
                     KeyCode::Enter => match app.active_block {
                         0 => {
-                            app.results = backend::get_search(&app.search_input).unwrap();
+                            app.clear_suggestions();
+                            let query = std::mem::take(&mut app.search_input);
+                            app.search(&query);
                             app.active_block = 1;
                             app.selected_item = 0;
-                            app.navigating_item = 0;
-                            app.video_state.select(Some(0));
-                            app.footer_text = format!("Search results for: {}", app.search_input);
-                            app.search_input.clear();
                         }
-                        1 => {
-                            app.selected_item = app.video_state.selected().unwrap();
-                            let selection = app.results[app.selected_item].url.clone();
-                            let title = app.results[app.selected_item].title.clone();
-                            backend::play_selection(&selection);
-                            app.footer_text = format!("Playing: {}", title);
+                        1 if app.details.is_some() => {
+                            let details = app.details.as_ref().unwrap();
+                            let (id, title) = (details.id.clone(), details.title.clone());
+                            app.play_video(&id, &title);
                         }
+                        1 => match app.highlighted() {
+                            Some(r) if r.kind == ResultKind::Playlist => {
+                                let id = r.id.clone();
+                                app.open_playlist(&id);
+                            }
+                            Some(r) if r.kind == ResultKind::Channel => app.open_channel(),
+                            Some(r) => {
+                                let (id, title) = (r.id.clone(), r.title.clone());
+                                app.play_video(&id, &title);
+                            }
+                            None => {}
+                        },
                         _ => {}
                     },
                     KeyCode::Tab => {
+                        app.clear_suggestions();
                         app.active_block = (app.active_block + 1) % 3;
                         if app.active_block == 1 {
                             app.selected_item = 0;
@@ -292,6 +3270,10 @@ fn main() -> Result<()> {
                         }
                     }
                     KeyCode::Char('q') => {
+                        app.positions.save().ok();
+                        if app.shown_thumbnail.is_some() && app.graphics == Graphics::Kitty {
+                            stdout().write_all(thumbnails::KITTY_CLEAR.as_bytes())?;
+                        }
                         break;
                     }
                     _ => {}
@@ -303,3 +3285,61 @@ fn main() -> Result<()> {
     disable_raw_mode()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(value: u32) -> std::result::Result<u32, String> {
+        Ok(value)
+    }
+
+    /// A full cache of three, "a" used least recently.
+    fn full() -> Lru<u32> {
+        let mut cache = Lru::new(3);
+        cache.insert("a".to_string(), ok(1));
+        cache.insert("b".to_string(), ok(2));
+        cache.insert("c".to_string(), ok(3));
+        cache
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        let mut cache = full();
+        cache.insert("d".to_string(), ok(4));
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.get("d"), Some(&ok(4)));
+        assert_eq!(cache.order, ["b", "c", "d"]);
+        assert_eq!(cache.entries.len(), 3);
+    }
+
+    #[test]
+    fn touch_keeps_an_entry_around() {
+        let mut cache = full();
+        assert!(cache.touch("a"));
+        assert!(!cache.touch("missing"));
+        cache.insert("d".to_string(), ok(4));
+        assert_eq!(cache.get("a"), Some(&ok(1)));
+        assert!(cache.get("b").is_none());
+    }
+
+    #[test]
+    fn replacing_an_entry_uses_it() {
+        let mut cache = full();
+        cache.insert("a".to_string(), Err("gone".to_string()));
+        assert_eq!(cache.order, ["b", "c", "a"]);
+        cache.insert("d".to_string(), ok(4));
+        assert_eq!(cache.get("a"), Some(&Err("gone".to_string())));
+        assert!(cache.get("b").is_none());
+    }
+
+    #[test]
+    fn retain_ok_drops_failures_from_both() {
+        let mut cache = full();
+        cache.insert("b".to_string(), Err("timed out".to_string()));
+        cache.retain_ok();
+        assert!(cache.get("b").is_none());
+        assert_eq!(cache.order, ["a", "c"]);
+        assert!(!cache.touch("b"));
+    }
+}